@@ -0,0 +1,76 @@
+//! Measures how fast the client applies updates to a server signal.
+//!
+//! Run with `cargo bench --features testing --bench apply`, which applies the updates through
+//! a `MockSse` in place of a browser. Add the `in-place-patch` feature to compare against
+//! applying the patches in place. The page of `examples/apply-bench` measures the same in the
+//! browser.
+
+use std::time::{Duration, Instant};
+
+use leptos::{create_runtime, SignalGetUntracked};
+use leptos_sse::{create_sse_field, create_sse_signal, MockSse, ServerSignalUpdate};
+use serde::{Deserialize, Serialize};
+
+const UPDATES: u64 = 200;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Document {
+    title: String,
+    body: String,
+    views: u64,
+}
+
+/// Applies `UPDATES` updates from `value`, each built by `change`, to a signal of the whole
+/// document, or only following its views with `create_sse_field` if `field` is set.
+fn bench(label: &str, value: Document, field: bool, change: impl Fn(&mut Document, u64)) {
+    let runtime = create_runtime();
+    let sse = MockSse::provide();
+    let views: Box<dyn Fn() -> u64> = if field {
+        let views = create_sse_field::<Document, u64>("document", "/views");
+        Box::new(move || views.get_untracked().unwrap_or_default())
+    } else {
+        let signal = create_sse_signal::<Document>("document");
+        Box::new(move || signal.get_untracked().views)
+    };
+    sse.push(ServerSignalUpdate::new("document", &Document::default(), &value).unwrap());
+
+    // Built up front, so only applying them is measured
+    let mut old = value;
+    let updates: Vec<_> = (0..UPDATES)
+        .map(|update| {
+            let mut new = old.clone();
+            change(&mut new, update);
+            let update = ServerSignalUpdate::new("document", &old, &new).unwrap();
+            old = new;
+            update
+        })
+        .collect();
+
+    let start = Instant::now();
+    for update in updates {
+        sse.push(update);
+    }
+    let elapsed: Duration = start.elapsed();
+    assert_eq!(views(), old.views);
+    println!("{label:<14} {:>10.2?} per update", elapsed / UPDATES as u32);
+    runtime.dispose();
+}
+
+fn main() {
+    let document = Document {
+        title: "document".to_owned(),
+        body: "lorem ipsum ".repeat(100_000),
+        views: 0,
+    };
+    for (label, field) in [("counter", false), ("counter field", true)] {
+        bench(label, document.clone(), field, |document, update| {
+            document.views = update + 1;
+        });
+    }
+    for (label, field) in [("body", false), ("body field", true)] {
+        bench(label, document.clone(), field, |document, update| {
+            document.body.push_str(&update.to_string());
+            document.views = update + 1;
+        });
+    }
+}
@@ -0,0 +1,75 @@
+//! Measures how fast a [`ServerSentEvents`] stream turns values into events.
+//!
+//! Run with `cargo bench --features ssr,axum`, adding `cbor`, `messagepack` or `compress` to
+//! compare the codecs.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::{executor::block_on, stream, StreamExt};
+use leptos_sse::axum::ServerSentEvents;
+use leptos_sse::Codec;
+use serde::Serialize;
+
+const ROUNDS: u32 = 20;
+
+#[derive(Clone, Serialize)]
+struct Row {
+    id: u32,
+    name: String,
+    score: f64,
+}
+
+/// The values of a large signal, of which a single row changes between updates.
+fn values(rows: u32, updates: u32) -> Vec<Vec<Row>> {
+    let mut value: Vec<Row> = (0..rows)
+        .map(|id| Row {
+            id,
+            name: format!("row {id}"),
+            score: 0.0,
+        })
+        .collect();
+    (0..updates)
+        .map(|update| {
+            value[(update % rows) as usize].score += 1.0;
+            value.clone()
+        })
+        .collect()
+}
+
+fn bench(label: &str, codec: Codec, values: &[Vec<Row>]) {
+    let bytes = Arc::new(AtomicUsize::new(0));
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..ROUNDS {
+        // Cloned up front, so only the stream itself is measured
+        let source = values.to_vec();
+        let stream = stream::iter(source.into_iter().map(Ok::<_, axum::BoxError>));
+        let counter = bytes.clone();
+        let sse = ServerSentEvents::new("rows", stream)
+            .unwrap()
+            .with_codec(codec)
+            .with_metrics(move |metrics| {
+                counter.fetch_add(metrics.bytes(), Ordering::Relaxed);
+            });
+        let start = Instant::now();
+        let events = block_on(sse.count());
+        elapsed += start.elapsed();
+        assert_eq!(events, values.len());
+    }
+    let updates = ROUNDS * values.len() as u32;
+    println!(
+        "{label:<12} {:>10.2?} per update, {:>6} bytes per update",
+        elapsed / updates,
+        bytes.load(Ordering::Relaxed) / updates as usize,
+    );
+}
+
+fn main() {
+    let values = values(1_000, 100);
+    bench("json", Codec::Json, &values);
+    #[cfg(feature = "cbor")]
+    bench("cbor", Codec::Cbor, &values);
+    #[cfg(feature = "messagepack")]
+    bench("messagepack", Codec::MessagePack, &values);
+}
@@ -10,7 +10,7 @@ pub struct Count {
 #[component]
 pub fn App() -> impl IntoView {
     // Provide sse connection
-    leptos_sse::provide_sse("http://localhost:3000/sse").unwrap();
+    leptos_sse::provide_sse("/sse").unwrap();
 
     // Create server signal
     let count = create_sse_signal::<Count>("counter");
@@ -42,9 +42,8 @@ pub fn main() {
 #[cfg(feature = "ssr")]
 pub async fn handle_sse() -> impl actix_web::Responder {
     use actix_example::app::Count;
-    use actix_web_lab::sse;
     use futures::stream;
-    use leptos_sse::ServerSentEvents;
+    use leptos_sse::actix::ServerSentEvents;
     use std::time::Duration;
     use tokio_stream::StreamExt as _;
 
@@ -59,5 +58,5 @@ pub async fn handle_sse() -> impl actix_web::Responder {
         .throttle(Duration::from_secs(1)),
     )
     .unwrap();
-    sse::Sse::from_stream(stream).with_keep_alive(Duration::from_secs(5))
+    stream.into_responder(Duration::from_secs(5))
 }
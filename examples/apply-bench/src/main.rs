@@ -0,0 +1,93 @@
+//! Measures how fast the client applies updates to a server signal in the browser.
+//!
+//! This is the page counterpart of `benches/apply.rs`, timing the updates with the clock of
+//! the browser and writing the results to the page.
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "wasm32")] {
+        use leptos::{create_runtime, SignalGetUntracked};
+        use leptos_sse::{create_sse_signal, MockSse, ServerSignalUpdate};
+        use serde::{Deserialize, Serialize};
+
+        const UPDATES: u64 = 200;
+
+        #[derive(Clone, Default, Serialize, Deserialize)]
+        struct Document {
+            title: String,
+            body: String,
+            views: u64,
+        }
+
+        /// The current time in milliseconds, from the clock of the browser.
+        fn now() -> f64 {
+            web_sys::window()
+                .and_then(|window| window.performance())
+                .map(|performance| performance.now())
+                .unwrap_or_default()
+        }
+
+        /// Applies `UPDATES` updates from `value`, each built by `change`, and returns the result
+        /// line.
+        fn bench(label: &str, value: Document, change: impl Fn(&mut Document, u64)) -> String {
+            let runtime = create_runtime();
+            let sse = MockSse::provide();
+            let signal = create_sse_signal::<Document>("document");
+            sse.push(ServerSignalUpdate::new("document", &Document::default(), &value).unwrap());
+
+            // Built up front, so only applying them is measured
+            let mut old = value;
+            let updates: Vec<_> = (0..UPDATES)
+                .map(|update| {
+                    let mut new = old.clone();
+                    change(&mut new, update);
+                    let update = ServerSignalUpdate::new("document", &old, &new).unwrap();
+                    old = new;
+                    update
+                })
+                .collect();
+
+            let start = now();
+            for update in updates {
+                sse.push(update);
+            }
+            let elapsed = now() - start;
+            assert_eq!(signal.get_untracked().views, old.views);
+            runtime.dispose();
+            format!("{label:<12} {:>10.3}ms per update", elapsed / UPDATES as f64)
+        }
+
+        fn main() {
+            console_error_panic_hook::set_once();
+
+            let document = Document {
+                title: "document".to_owned(),
+                body: "lorem ipsum ".repeat(100_000),
+                views: 0,
+            };
+            let results = [
+                bench("counter", document.clone(), |document, update| {
+                    document.views = update + 1;
+                }),
+                bench("body", document, |document, update| {
+                    document.body.push_str(&update.to_string());
+                    document.views = update + 1;
+                }),
+            ];
+            let patcher = if cfg!(feature = "in-place-patch") {
+                "in-place-patch"
+            } else {
+                "json-patch"
+            };
+
+            let body = web_sys::window()
+                .and_then(|window| window.document())
+                .and_then(|document| document.body())
+                .expect("the page has a body");
+            body.set_inner_html(&format!("<h1>{patcher}</h1><pre>{}</pre>", results.join("\n")));
+        }
+    } else {
+        fn main() {
+            eprintln!("Serve this page with `trunk serve --release`, see its README.");
+        }
+    }
+}
@@ -48,7 +48,7 @@ pub fn main() {
 
 #[cfg(feature = "ssr")]
 use {
-    axum::response::sse::{Event, KeepAlive, Sse},
+    axum::response::sse::{Event, Sse},
     futures::stream::Stream,
 };
 
@@ -56,7 +56,7 @@ use {
 async fn handle_sse() -> Sse<impl Stream<Item = Result<Event, axum::BoxError>>> {
     use axum_example::app::Count;
     use futures::stream;
-    use leptos_sse::ServerSentEvents;
+    use leptos_sse::axum::ServerSentEvents;
     use std::time::Duration;
     use tokio_stream::StreamExt as _;
 
@@ -71,5 +71,5 @@ async fn handle_sse() -> Sse<impl Stream<Item = Result<Event, axum::BoxError>>>
         .throttle(Duration::from_secs(1)),
     )
     .unwrap();
-    Sse::new(stream).keep_alive(KeepAlive::default())
+    stream.into_responder(Duration::from_secs(15))
 }
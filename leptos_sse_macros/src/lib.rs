@@ -0,0 +1,81 @@
+//! Derive macros for [leptos_sse](https://crates.io/crates/leptos_sse).
+//!
+//! Use them through the `derive` feature of `leptos_sse` rather than depending on this crate.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, LitStr};
+
+/// Derives `leptos_sse::SseSignal`, naming the signal after the type.
+///
+/// The name can be set with `#[sse_signal(name = "...")]`.
+#[proc_macro_derive(SseSignal, attributes(sse_signal))]
+pub fn derive_sse_signal(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_sse_signal(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Derives `leptos_sse::SseSignal`, along with helpers creating both ends of the signal.
+///
+/// This generates `sse_signal()`, creating the signal on the client, and `sse_channel(buffer)`,
+/// creating the channel updating it on the server. The latter only exists when the `ssr`
+/// feature and a server integration of `leptos_sse` are enabled.
+///
+/// The name can be set with `#[sse_signal(name = "...")]`.
+#[proc_macro_derive(ServerSignal, attributes(sse_signal))]
+pub fn derive_server_signal(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let sse_signal = match expand_sse_signal(&input) {
+        Ok(tokens) => tokens,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        #sse_signal
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Creates the server signal on the client, see `leptos_sse::create_sse_signal_for`.
+            pub fn sse_signal() -> ::leptos_sse::__private::ReadSignal<Self>
+            where
+                Self: ::core::default::Default
+                    + ::leptos_sse::__private::Serialize
+                    + ::leptos_sse::__private::DeserializeOwned,
+            {
+                ::leptos_sse::create_sse_signal_for::<Self>()
+            }
+
+            ::leptos_sse::__sse_channel!();
+        }
+    }
+    .into()
+}
+
+fn expand_sse_signal(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut name = LitStr::new(&input.ident.to_string(), input.ident.span());
+    for attr in &input.attrs {
+        if !attr.path().is_ident("sse_signal") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = meta.value()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported sse_signal attribute, expected `name`"))
+            }
+        })?;
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::leptos_sse::SseSignal for #ident #ty_generics #where_clause {
+            const NAME: &'static str = #name;
+        }
+    })
+}
@@ -1,69 +1,92 @@
-use std::borrow::Cow;
 use std::error::Error;
 use std::pin::Pin;
 use std::task::Poll;
+use std::time::Duration;
 
 use actix_web_lab::sse::{self, Event};
-use futures::stream::{Stream, StreamExt, TryStream};
-use pin_project_lite::pin_project;
+use futures::stream::{Stream, TryStream};
 use serde::Serialize;
-use serde_json::Value;
-use tokio::sync::mpsc;
 pub use tokio::sync::mpsc::error::{SendError, TrySendError};
-use tokio_stream::wrappers::ReceiverStream;
 
-use crate::ServerSignalUpdate;
+#[cfg(feature = "compress")]
+use crate::server::GzipStream;
+use crate::server::{event_id, sealed, Backend, SignalEvent, TimerBackend};
+pub use crate::server::{BroadcastSender, LossySender, UnboundedSender};
 
 type BoxError = Box<dyn Error>;
 
-pin_project! {
-    /// A signal owned by the server which writes to the SSE when mutated.
-    #[derive(Clone, Debug)]
-    pub struct ServerSentEvents<S> {
-        name: Cow<'static, str>,
-        #[pin]
-        stream: S,
-        json_value: Value,
-    }
+/// The [`Backend`] of actix-web, whose events are [`Event`]s of actix-web-lab.
+///
+/// The events of actix-web-lab have no retry field, so [`ServerSentEvents::with_retry`] is not
+/// offered, and the retry duration is set on the [`sse::Sse`] responder instead.
+///
+/// [`ServerSentEvents::with_retry`]: crate::ServerSentEvents::with_retry
+#[derive(Clone, Copy, Debug)]
+pub struct Actix;
+
+impl sealed::Sealed for Actix {}
+
+impl Backend for Actix {
+    type Error = BoxError;
+    type Event = Event;
 }
 
+impl TimerBackend for Actix {}
+
+/// A signal owned by the server which writes to the SSE when mutated, as the [`Event`]s of an
+/// actix-web-lab [`sse::Sse`] responder, see [`ServerSentEvents::into_responder`].
+pub type ServerSentEvents<S> = crate::server::ServerSentEvents<S, Actix>;
+
+/// Sender half of a server-sent events stream, whose custom events are [`Event`]s.
+pub type Sender<T> = crate::server::Sender<T, Actix>;
+
+/// Creates the SSE of each connection of a broadcast channel, see
+/// [`ServerSentEvents::broadcast_channel`].
+pub type BroadcastSubscriber<T> = crate::server::BroadcastSubscriber<T, Actix>;
+
+/// The stream of a [`ServerSentEvents`] created by [`ServerSentEvents::channel_for`].
+pub type ChannelStream<T> = crate::server::ChannelStream<T, Actix>;
+
+/// The stream of a [`ServerSentEvents`] created by [`BroadcastSubscriber::subscribe`].
+pub type BroadcastChannelStream<T> = crate::server::BroadcastChannelStream<T, Actix>;
+
 impl<S> ServerSentEvents<S> {
-    /// Create a new [`ServerSentEvents`] a stream, initializing `T` to default.
+    /// Wrap the stream in a [`sse::Sse`] responder, which sends a keep alive comment whenever
+    /// no event was sent for `keep_alive`.
     ///
-    /// This function can fail if serilization of `T` fails.
-    pub fn new<T>(name: impl Into<Cow<'static, str>>, stream: S) -> Result<Self, serde_json::Error>
+    /// Behind a proxy which buffers responses, such as nginx, add the headers of
+    /// [`sse_headers`](crate::sse_headers) so the events are not held back.
+    pub fn into_responder(self, keep_alive: Duration) -> sse::Sse<Self>
     where
-        T: Default + Serialize,
-        S: TryStream<Ok = T, Error = BoxError>,
+        S: TryStream<Error = BoxError> + 'static,
+        S::Ok: Serialize,
     {
-        Ok(ServerSentEvents {
-            name: name.into(),
-            stream,
-            json_value: serde_json::to_value(T::default())?,
-        })
+        sse::Sse::from_stream(self).with_keep_alive(keep_alive)
     }
 
-    /// Create a server-sent-events (SSE) channel pair.
+    /// Wrap the stream in a [`sse::Sse`] responder like [`ServerSentEvents::into_responder`],
+    /// gzipping the whole body.
     ///
-    /// The `buffer` argument controls how many unsent messages can be stored without waiting.
-    ///
-    /// The first item in the tuple is the MPSC channel sender half.
-    pub fn channel<T>(
-        name: impl Into<Cow<'static, str>>,
-        buffer: usize,
-    ) -> Result<
-        (
-            Sender<T>,
-            ServerSentEvents<impl TryStream<Ok = T, Error = BoxError>>,
-        ),
-        serde_json::Error,
-    >
+    /// Unlike compression middleware, which buffers the body and so holds back events, the
+    /// compressed data of each event is flushed right away. Browsers decompress the body of an
+    /// `EventSource` transparently, but only send this to clients which accept gzip. The
+    /// response is marked as encoded, so the `Compress` middleware leaves it alone.
+    #[cfg(feature = "compress")]
+    pub fn into_compressed_responder(self, keep_alive: Duration) -> actix_web::HttpResponse
     where
-        T: Default + Serialize,
+        S: TryStream<Error = BoxError> + 'static,
+        S::Ok: Serialize,
     {
-        let (sender, receiver) = mpsc::channel::<T>(buffer);
-        let stream = ReceiverStream::new(receiver).map(Ok);
-        Ok((Sender(sender), ServerSentEvents::new(name, stream)?))
+        use actix_web::body::MessageBody;
+        use futures::stream;
+
+        let mut sse = Box::pin(self.into_responder(keep_alive));
+        let events = stream::poll_fn(move |cx| sse.as_mut().poll_next(cx));
+        actix_web::HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header(("cache-control", "no-cache"))
+            .insert_header(("content-encoding", "gzip"))
+            .streaming(GzipStream::new(events))
     }
 }
 
@@ -78,44 +101,41 @@ where
         self: Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        let this = self.project();
-        match this.stream.try_poll_next(cx) {
-            Poll::Ready(Some(Ok(value))) => {
-                let new_json = serde_json::to_value(value)?;
-                let update = ServerSignalUpdate::new_from_json::<S::Item>(
-                    this.name.clone(),
-                    this.json_value,
-                    &new_json,
+        match self.poll_signal(cx) {
+            // actix-web-lab has no retry field, and comments are events of their own
+            Poll::Ready(Some(Ok(SignalEvent::Update {
+                id,
+                name,
+                data,
+                meta,
+            }))) => {
+                let event = Event::Data(
+                    sse::Data::new(data.as_str())
+                        .id(event_id(id, meta.as_ref()))
+                        .event(name.into_owned()),
                 );
-                *this.json_value = new_json;
-                let event = Event::Data(sse::Data::new_json(update)?);
                 Poll::Ready(Some(Ok(event)))
             }
+            Poll::Ready(Some(Ok(SignalEvent::Error(error)))) => {
+                let event = Event::Data(sse::Data::new_json(error)?.event("error"));
+                Poll::Ready(Some(Ok(event)))
+            }
+            // actix-web-lab has no retry event, the retry duration is set on the `Sse` responder
+            Poll::Ready(Some(Ok(SignalEvent::Retry(_)))) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Heartbeat))) => {
+                Poll::Ready(Some(Ok(Event::Comment("".into()))))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Close(name)))) => {
+                let event = Event::Data(sse::Data::new(name.into_owned()).event("close"));
+                Poll::Ready(Some(Ok(event)))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Custom(event)))) => Poll::Ready(Some(Ok(event))),
             Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err))),
             Poll::Ready(None) => Poll::Ready(None),
             Poll::Pending => Poll::Pending,
         }
     }
 }
-
-/// Sender half of a server-sent events stream.
-#[derive(Clone, Debug)]
-pub struct Sender<T>(mpsc::Sender<T>);
-
-impl<T> Sender<T> {
-    /// Send an SSE message.
-    pub async fn send(&self, value: T) -> Result<(), SendError<T>>
-    where
-        T: Serialize,
-    {
-        self.0.send(value).await
-    }
-
-    /// Attempts to immediately send an SSE message.
-    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>>
-    where
-        T: Serialize,
-    {
-        self.0.try_send(value)
-    }
-}
@@ -1,66 +1,91 @@
-use std::borrow::Cow;
 use std::pin::Pin;
 use std::task::Poll;
+use std::time::Duration;
 
-use axum::response::sse::Event;
-use futures::stream::{Stream, StreamExt, TryStream};
-use pin_project_lite::pin_project;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::stream::{Stream, TryStream};
 use serde::Serialize;
-use serde_json::Value;
-use tokio::sync::mpsc;
 pub use tokio::sync::mpsc::error::{SendError, TrySendError};
-use tokio_stream::wrappers::ReceiverStream;
-
-use crate::ServerSignalUpdate;
-
-pin_project! {
-    /// A signal owned by the server which writes to the SSE when mutated.
-    #[derive(Clone, Debug)]
-    pub struct ServerSentEvents<S> {
-        name: Cow<'static, str>,
-        #[pin]
-        stream: S,
-        json_value: Value,
-    }
+
+#[cfg(feature = "compress")]
+use crate::server::GzipStream;
+use crate::server::{
+    event_id, sealed, Backend, EventMeta, RetryBackend, SignalEvent, TimerBackend,
+};
+pub use crate::server::{BroadcastSender, LossySender, UnboundedSender};
+
+/// The [`Backend`] of axum, whose events are [`Event`]s.
+#[derive(Clone, Copy, Debug)]
+pub struct Axum;
+
+impl sealed::Sealed for Axum {}
+
+impl Backend for Axum {
+    type Error = axum::BoxError;
+    type Event = Event;
 }
 
+impl RetryBackend for Axum {}
+
+impl TimerBackend for Axum {}
+
+/// A signal owned by the server which writes to the SSE when mutated, as the [`Event`]s of an
+/// axum [`Sse`] response, see [`ServerSentEvents::into_responder`].
+pub type ServerSentEvents<S> = crate::server::ServerSentEvents<S, Axum>;
+
+/// Sender half of a server-sent events stream, whose custom events are [`Event`]s.
+pub type Sender<T> = crate::server::Sender<T, Axum>;
+
+/// Creates the SSE of each connection of a broadcast channel, see
+/// [`ServerSentEvents::broadcast_channel`].
+pub type BroadcastSubscriber<T> = crate::server::BroadcastSubscriber<T, Axum>;
+
+/// The stream of a [`ServerSentEvents`] created by [`ServerSentEvents::channel_for`].
+pub type ChannelStream<T> = crate::server::ChannelStream<T, Axum>;
+
+/// The stream of a [`ServerSentEvents`] created by [`BroadcastSubscriber::subscribe`].
+pub type BroadcastChannelStream<T> = crate::server::BroadcastChannelStream<T, Axum>;
+
 impl<S> ServerSentEvents<S> {
-    /// Create a new [`ServerSentEvents`] a stream, initializing `T` to default.
+    /// Wrap the stream in a [`Sse`] response, which sends a keep alive comment whenever no
+    /// event was sent for `keep_alive`.
     ///
-    /// This function can fail if serilization of `T` fails.
-    pub fn new<T>(name: impl Into<Cow<'static, str>>, stream: S) -> Result<Self, serde_json::Error>
+    /// Behind a proxy which buffers responses, such as nginx, add the headers of
+    /// [`sse_headers`](crate::sse_headers) so the events are not held back.
+    pub fn into_responder(self, keep_alive: Duration) -> Sse<Self>
     where
-        T: Default + Serialize,
-        S: TryStream<Ok = T, Error = axum::BoxError>,
+        S: TryStream<Error = axum::BoxError> + Send + 'static,
+        S::Ok: Serialize,
     {
-        Ok(ServerSentEvents {
-            name: name.into(),
-            stream,
-            json_value: serde_json::to_value(T::default())?,
-        })
+        Sse::new(self).keep_alive(KeepAlive::new().interval(keep_alive))
     }
 
-    /// Create a server-sent-events (SSE) channel pair.
+    /// Wrap the stream in a [`Sse`] response like [`ServerSentEvents::into_responder`], gzipping
+    /// the whole body.
     ///
-    /// The `buffer` argument controls how many unsent messages can be stored without waiting.
-    ///
-    /// The first item in the tuple is the MPSC channel sender half.
-    pub fn channel<T>(
-        name: impl Into<Cow<'static, str>>,
-        buffer: usize,
-    ) -> Result<
-        (
-            Sender<T>,
-            ServerSentEvents<impl TryStream<Ok = T, Error = axum::BoxError>>,
-        ),
-        serde_json::Error,
-    >
+    /// Unlike compression middleware, which buffers the body and so holds back events, the
+    /// compressed data of each event is flushed right away. Browsers decompress the body of an
+    /// `EventSource` transparently, but only send this to clients which accept gzip. The
+    /// response is marked as encoded, so compression middleware leaves it alone.
+    #[cfg(feature = "compress")]
+    pub fn into_compressed_responder(self, keep_alive: Duration) -> axum::response::Response
     where
-        T: Default + Serialize,
+        S: TryStream<Error = axum::BoxError> + Send + 'static,
+        S::Ok: Serialize,
     {
-        let (sender, receiver) = mpsc::channel::<T>(buffer);
-        let stream = ReceiverStream::new(receiver).map(Ok);
-        Ok((Sender(sender), ServerSentEvents::new(name, stream)?))
+        use axum::body::Body;
+        use axum::http::{header, HeaderValue};
+        use axum::response::{IntoResponse, Response};
+        use futures::StreamExt;
+
+        let (mut parts, body) = self.into_responder(keep_alive).into_response().into_parts();
+        let events = body
+            .into_data_stream()
+            .map(|chunk| chunk.map_err(axum::BoxError::from));
+        parts
+            .headers
+            .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        Response::from_parts(parts, Body::from_stream(GzipStream::new(events)))
     }
 }
 
@@ -75,44 +100,44 @@ where
         self: Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        let this = self.project();
-        match this.stream.try_poll_next(cx) {
-            Poll::Ready(Some(Ok(value))) => {
-                let new_json = serde_json::to_value(value)?;
-                let update = ServerSignalUpdate::new_from_json::<S::Item>(
-                    this.name.clone(),
-                    this.json_value,
-                    &new_json,
-                );
-                *this.json_value = new_json;
-                let event = Event::default().json_data(update)?;
+        match self.poll_signal(cx) {
+            Poll::Ready(Some(Ok(SignalEvent::Update {
+                id,
+                name,
+                data,
+                meta,
+            }))) => {
+                let mut event = Event::default()
+                    .id(event_id(id, meta.as_ref()))
+                    .event(name)
+                    .data(data.as_str());
+                if let Some(retry) = meta.as_ref().and_then(EventMeta::retry) {
+                    event = event.retry(retry);
+                }
+                if let Some(comment) = meta.as_ref().and_then(EventMeta::comment) {
+                    event = event.comment(comment);
+                }
                 Poll::Ready(Some(Ok(event)))
             }
+            Poll::Ready(Some(Ok(SignalEvent::Error(error)))) => {
+                let event = Event::default()
+                    .event("error")
+                    .data(serde_json::to_string(&error)?);
+                Poll::Ready(Some(Ok(event)))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Retry(retry)))) => {
+                Poll::Ready(Some(Ok(Event::default().retry(retry))))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Heartbeat))) => {
+                Poll::Ready(Some(Ok(Event::default().comment(""))))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Close(name)))) => {
+                Poll::Ready(Some(Ok(Event::default().event("close").data(name))))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Custom(event)))) => Poll::Ready(Some(Ok(event))),
             Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err))),
             Poll::Ready(None) => Poll::Ready(None),
             Poll::Pending => Poll::Pending,
         }
     }
 }
-
-/// Sender half of a server-sent events stream.
-#[derive(Clone, Debug)]
-pub struct Sender<T>(mpsc::Sender<T>);
-
-impl<T> Sender<T> {
-    /// Send an SSE message.
-    pub async fn send(&self, value: T) -> Result<(), SendError<T>>
-    where
-        T: Serialize,
-    {
-        self.0.send(value).await
-    }
-
-    /// Attempts to immediately send an SSE message.
-    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>>
-    where
-        T: Serialize,
-    {
-        self.0.try_send(value)
-    }
-}
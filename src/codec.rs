@@ -0,0 +1,376 @@
+use std::fmt;
+use std::io::Write;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::{ServerSignalBatch, ServerSignalUpdate};
+
+/// Prefix marking a base64 encoded CBOR [`ServerSignalUpdate`] in the SSE data field.
+#[cfg(feature = "cbor")]
+const CBOR_PREFIX: &str = "cbor:";
+
+/// Prefix marking a base64 encoded MessagePack [`ServerSignalUpdate`] in the SSE data field.
+#[cfg(feature = "messagepack")]
+const MSGPACK_PREFIX: &str = "msgpack:";
+
+/// Prefix marking base64 encoded deflated data in the SSE data field.
+const DEFLATE_PREFIX: &str = "deflate:";
+
+cfg_if::cfg_if! {
+    // The events are named by the server, and routed by the client
+    if #[cfg(any(target_arch = "wasm32", feature = "testing", all(feature = "ssr", any(feature = "actix", feature = "axum", feature = "warp", feature = "poem", feature = "rocket", feature = "tide", feature = "tower"))))] {
+        /// Suffix of the name of the events carrying compressed updates, after the name of the signal.
+        const COMPRESSED_EVENT_SUFFIX: &str = "-deflate";
+
+        /// The name of the events carrying the compressed updates of the signal `name`.
+        pub(crate) fn compressed_event_type(name: &str) -> String {
+            format!("{name}{COMPRESSED_EVENT_SUFFIX}")
+        }
+    }
+}
+
+/// The signal of a compressed update sent as an `event_type` event, or `None` if `data` is
+/// not compressed.
+///
+/// The name of the event only routes the update, as a signal may be named with the suffix too.
+/// Whether it is compressed is told by the prefix of its data, which json never starts with.
+#[cfg(any(target_arch = "wasm32", feature = "testing"))]
+pub(crate) fn compressed_event_signal<'a>(event_type: &'a str, data: &str) -> Option<&'a str> {
+    event_type
+        .strip_suffix(COMPRESSED_EVENT_SUFFIX)
+        .filter(|_| data.starts_with(DEFLATE_PREFIX))
+}
+
+/// The format [`ServerSignalUpdate`]s are encoded with in the data field of a SSE.
+///
+/// Binary formats are base64 encoded, and marked with a prefix so the client can tell them
+/// apart. Json updates are always understood, regardless of the enabled features.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Codec {
+    /// Plain json.
+    Json,
+    /// [CBOR](https://cbor.io), prefixed with `cbor:`.
+    #[cfg(feature = "cbor")]
+    Cbor,
+    /// [MessagePack](https://msgpack.org), prefixed with `msgpack:`.
+    #[cfg(feature = "messagepack")]
+    MessagePack,
+}
+
+impl Default for Codec {
+    /// CBOR or MessagePack if their feature is enabled, json otherwise.
+    fn default() -> Self {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "cbor")] {
+                Codec::Cbor
+            } else if #[cfg(feature = "messagepack")] {
+                Codec::MessagePack
+            } else {
+                Codec::Json
+            }
+        }
+    }
+}
+
+impl Codec {
+    /// The codecs marked with a prefix, and the feature required to decode them.
+    const PREFIXED: [(&'static str, &'static str); 2] =
+        [("cbor:", "cbor"), ("msgpack:", "messagepack")];
+
+    /// Encodes `update` for the data field of a SSE, writing it to `writer`.
+    ///
+    /// The update is serialized straight into `writer`, without an intermediate buffer.
+    pub(crate) fn encode_into(
+        self,
+        update: &ServerSignalUpdate,
+        writer: impl Write,
+    ) -> Result<(), CodecError> {
+        match self {
+            Codec::Json => Ok(serde_json::to_writer(writer, update)?),
+            #[cfg(feature = "cbor")]
+            Codec::Cbor => {
+                let mut writer = base64_writer(CBOR_PREFIX, writer)?;
+                ciborium::into_writer(update, &mut writer).map_err(CodecError::new)?;
+                writer.finish().map_err(CodecError::new)?;
+                Ok(())
+            }
+            #[cfg(feature = "messagepack")]
+            Codec::MessagePack => {
+                let mut writer = base64_writer(MSGPACK_PREFIX, writer)?;
+                rmp_serde::encode::write_named(&mut writer, update).map_err(CodecError::new)?;
+                writer.finish().map_err(CodecError::new)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Picks the codec of `data` from its prefix, returning the data without the prefix.
+    fn detect(data: &str) -> Result<(Self, &str), CodecError> {
+        #[cfg(feature = "cbor")]
+        if let Some(data) = data.strip_prefix(CBOR_PREFIX) {
+            return Ok((Codec::Cbor, data));
+        }
+        #[cfg(feature = "messagepack")]
+        if let Some(data) = data.strip_prefix(MSGPACK_PREFIX) {
+            return Ok((Codec::MessagePack, data));
+        }
+        for (prefix, feature) in Codec::PREFIXED {
+            if data.starts_with(prefix) {
+                return Err(CodecError::new(format!(
+                    "received an update encoded as `{prefix}`, but the `{feature}` feature is not enabled"
+                )));
+            }
+        }
+        Ok((Codec::Json, data))
+    }
+
+    fn decode<T: DeserializeOwned>(self, data: &str) -> Result<T, CodecError> {
+        match self {
+            Codec::Json => Ok(serde_json::from_str(data)?),
+            #[cfg(feature = "cbor")]
+            Codec::Cbor => {
+                let bytes = base64_decode(data)?;
+                ciborium::from_reader(bytes.as_slice()).map_err(CodecError::new)
+            }
+            #[cfg(feature = "messagepack")]
+            Codec::MessagePack => {
+                let bytes = base64_decode(data)?;
+                rmp_serde::from_slice(&bytes).map_err(CodecError::new)
+            }
+        }
+    }
+}
+
+/// A format for the data field of a SSE, which [`ServerSignalUpdate`]s are encoded with.
+///
+/// [`Codec`] covers the formats of this crate. Implement this trait to use any other, for example
+/// to encrypt the updates or to match a proprietary format.
+///
+/// The server and the client must use the same codec: pass it to `ServerSentEvents::with_codec`
+/// on the server, and to [`SseOptions::with_codec`](crate::SseOptions::with_codec) on the client.
+/// Unlike the formats of [`Codec`], which are told apart by their payload, a custom codec is
+/// used for every update, so a client with another codec fails to decode any of them.
+///
+/// Updates encoded with a custom codec are not compressed, and the client cannot decode a
+/// [`ServerSignalBatch`] with it. The encoded data may span several lines, but must not contain
+/// a carriage return, which SSE cannot send.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(any(feature = "cbor", feature = "messagepack", feature = "compress", feature = "raw"))]
+/// # mod app {
+/// # use base64::prelude::*;
+/// # use leptos_sse::{CodecError, ServerSignalUpdate, SseCodec};
+/// # pub struct Key;
+/// # impl Key {
+/// #     fn encrypt(&self, bytes: &[u8]) -> Vec<u8> {
+/// #         bytes.to_vec()
+/// #     }
+/// #     fn decrypt(&self, bytes: &[u8]) -> Vec<u8> {
+/// #         bytes.to_vec()
+/// #     }
+/// # }
+/// struct Encrypted(Key);
+///
+/// impl SseCodec for Encrypted {
+///     fn encode(&self, update: &ServerSignalUpdate) -> Result<String, CodecError> {
+///         let json = serde_json::to_vec(update).map_err(CodecError::new)?;
+///         Ok(BASE64_STANDARD.encode(self.0.encrypt(&json)))
+///     }
+///
+///     fn decode(&self, data: &str) -> Result<ServerSignalUpdate, CodecError> {
+///         let bytes = BASE64_STANDARD.decode(data).map_err(CodecError::new)?;
+///         serde_json::from_slice(&self.0.decrypt(&bytes)).map_err(CodecError::new)
+///     }
+/// }
+/// # }
+/// ```
+pub trait SseCodec: Send + Sync + 'static {
+    /// Encodes `update` for the data field of a SSE.
+    fn encode(&self, update: &ServerSignalUpdate) -> Result<String, CodecError>;
+
+    /// Decodes an update from the data field of a SSE.
+    fn decode(&self, data: &str) -> Result<ServerSignalUpdate, CodecError>;
+
+    /// The built-in codec this is, whose updates are encoded without an intermediate string,
+    /// and may be compressed or batched.
+    #[doc(hidden)]
+    fn builtin(&self) -> Option<Codec> {
+        None
+    }
+}
+
+impl SseCodec for Codec {
+    fn encode(&self, update: &ServerSignalUpdate) -> Result<String, CodecError> {
+        let mut data = Vec::new();
+        self.encode_into(update, &mut data)?;
+        String::from_utf8(data).map_err(CodecError::new)
+    }
+
+    /// Decodes an update, rejecting those encoded with any other codec.
+    fn decode(&self, data: &str) -> Result<ServerSignalUpdate, CodecError> {
+        match ServerSignalMessage::decode(data, Some(*self))? {
+            ServerSignalMessage::Update(update) => Ok(update),
+            ServerSignalMessage::Batch(batch) => Err(CodecError::new(format!(
+                "expected a single update, but received a batch of {}",
+                batch.updates.len()
+            ))),
+        }
+    }
+
+    fn builtin(&self) -> Option<Codec> {
+        Some(*self)
+    }
+}
+
+/// The data of a SSE received by the client, either a single update or a batch of them.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ServerSignalMessage {
+    Batch(ServerSignalBatch),
+    Update(ServerSignalUpdate),
+}
+
+impl ServerSignalMessage {
+    /// Decodes a message from the data field of a SSE.
+    ///
+    /// The codec is picked from the payload itself. If `expected` is set, updates encoded with
+    /// any other codec are rejected.
+    pub(crate) fn decode(data: &str, expected: Option<Codec>) -> Result<Self, CodecError> {
+        if let Some(_data) = data.strip_prefix(DEFLATE_PREFIX) {
+            cfg_if::cfg_if! {
+                if #[cfg(feature = "compress")] {
+                    return Self::decode(&decompress(_data)?, expected);
+                } else {
+                    return Err(CodecError::new(
+                        "received a compressed update, but the `compress` feature is not enabled",
+                    ));
+                }
+            }
+        }
+
+        let (codec, data) = Codec::detect(data)?;
+        match expected {
+            Some(expected) if expected != codec => Err(CodecError::new(format!(
+                "expected an update encoded as {expected:?}, but received {codec:?}"
+            ))),
+            _ => codec.decode(data),
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    // Compressed updates are only written by the server
+    if #[cfg(any(feature = "cbor", feature = "messagepack", all(feature = "compress", all(feature = "ssr", any(feature = "actix", feature = "axum", feature = "warp", feature = "poem", feature = "rocket", feature = "tide", feature = "tower")))))] {
+        /// Writes `prefix` to `writer`, returning a writer base64 encoding into it.
+        fn base64_writer<W: Write>(
+            prefix: &str,
+            mut writer: W,
+        ) -> Result<base64::write::EncoderWriter<'static, base64::engine::GeneralPurpose, W>, CodecError> {
+            writer
+                .write_all(prefix.as_bytes())
+                .map_err(CodecError::new)?;
+            Ok(base64::write::EncoderWriter::new(
+                writer,
+                &base64::engine::general_purpose::STANDARD,
+            ))
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "cbor",
+    feature = "messagepack",
+    feature = "compress",
+    all(feature = "raw", any(target_arch = "wasm32", feature = "testing"))
+))]
+fn base64_decode(data: &str) -> Result<Vec<u8>, CodecError> {
+    use base64::Engine;
+
+    base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(CodecError::new)
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "ssr", any(feature = "actix", feature = "axum", feature = "warp", feature = "poem", feature = "rocket", feature = "tide", feature = "tower")))] {
+        /// Encoded updates up to this size in bytes are not compressed by default.
+        #[cfg(feature = "compress")]
+        pub(crate) const DEFAULT_COMPRESSION_THRESHOLD: usize = 1024;
+
+        /// Encodes the bytes of a raw value for the SSE data field.
+        #[cfg(feature = "raw")]
+        pub(crate) fn encode_raw(bytes: &[u8]) -> String {
+            use base64::Engine;
+
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        }
+
+        /// Deflates encoded `data`, writing it to `writer`.
+        #[cfg(feature = "compress")]
+        pub(crate) fn compress(data: &[u8], writer: impl Write) -> Result<(), CodecError> {
+            use flate2::{write::DeflateEncoder, Compression};
+
+            let writer = base64_writer(DEFLATE_PREFIX, writer)?;
+            let mut encoder = DeflateEncoder::new(writer, Compression::default());
+            encoder.write_all(data).map_err(CodecError::new)?;
+            encoder
+                .finish()
+                .and_then(|mut writer| writer.finish())
+                .map_err(CodecError::new)?;
+            Ok(())
+        }
+    }
+}
+
+/// Decodes the bytes of a raw value encoded by `encode_raw`.
+#[cfg(all(feature = "raw", any(target_arch = "wasm32", feature = "testing")))]
+pub(crate) fn decode_raw(data: &str) -> Result<Vec<u8>, CodecError> {
+    base64_decode(data)
+}
+
+/// Inflates data deflated by `compress`.
+#[cfg(feature = "compress")]
+fn decompress(data: &str) -> Result<String, CodecError> {
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    let bytes = base64_decode(data)?;
+    let mut data = String::new();
+    DeflateDecoder::new(bytes.as_slice())
+        .read_to_string(&mut data)
+        .map_err(CodecError::new)?;
+    Ok(data)
+}
+
+/// An error encoding or decoding a [`ServerSignalUpdate`] for the SSE.
+#[derive(Debug)]
+pub struct CodecError(Box<dyn std::error::Error + Send + Sync>);
+
+impl CodecError {
+    /// Creates a new [`CodecError`], for example for the errors of an [`SseCodec`].
+    pub fn new(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        CodecError(err.into())
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+impl From<serde_json::Error> for CodecError {
+    fn from(err: serde_json::Error) -> Self {
+        CodecError::new(err)
+    }
+}
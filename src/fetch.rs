@@ -0,0 +1,444 @@
+//! A replacement for the browser `EventSource`, which reads the stream through `fetch`.
+//!
+//! The stream is parsed the way browsers parse it, and dispatched as the same `open`, `error`,
+//! `message` and named events, so the client handles both transports alike.
+
+use std::cell::Cell;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use js_sys::{Promise, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AbortController, Event, EventSource, EventSourceInit, EventTarget, Headers, MessageEvent,
+    MessageEventInit, ReadableStreamDefaultReader, ReadableStreamReadResult, RequestCredentials,
+    RequestInit, Response, TextDecodeOptions, TextDecoder,
+};
+
+/// The reconnection delay browsers use until the server sends a `retry` field, in milliseconds.
+const DEFAULT_RETRY: u32 = 3000;
+
+/// An event source which connects through `fetch`.
+///
+/// Like an `EventSource`, it reconnects when the stream ends or the connection is lost, sending
+/// the id of the last event, until it is closed. A response which is not a successful event
+/// stream closes it for good.
+#[derive(Clone, Debug)]
+pub(crate) struct FetchEventSource {
+    target: EventTarget,
+    state: Rc<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    url: String,
+    headers: Vec<(String, String)>,
+    with_credentials: bool,
+    ready_state: Cell<u16>,
+    // Aborts the current request once closed
+    abort: AbortController,
+}
+
+/// Why reading the stream stopped.
+enum Failure {
+    /// The connection was lost, and is opened again.
+    Network(JsValue),
+    /// The server did not respond with an event stream, which is not retried.
+    Rejected(String),
+}
+
+impl From<JsValue> for Failure {
+    fn from(err: JsValue) -> Self {
+        Failure::Network(err)
+    }
+}
+
+impl FetchEventSource {
+    /// Connects to `url`, sending `headers` along each request, and cookies along cross-origin
+    /// requests if `options` enable `withCredentials`.
+    pub(crate) fn new(
+        url: &str,
+        options: Option<&EventSourceInit>,
+        headers: &[(String, String)],
+    ) -> Result<Self, JsValue> {
+        let source = FetchEventSource {
+            target: EventTarget::new()?,
+            state: Rc::new(State {
+                url: url.to_owned(),
+                headers: headers.to_vec(),
+                with_credentials: options
+                    .and_then(|options| options.get_with_credentials())
+                    .unwrap_or(false),
+                ready_state: Cell::new(EventSource::CONNECTING),
+                abort: AbortController::new()?,
+            }),
+        };
+        wasm_bindgen_futures::spawn_local(source.clone().run());
+        Ok(source)
+    }
+
+    /// The state of the connection, as one of the `EventSource` ready states.
+    pub(crate) fn ready_state(&self) -> u16 {
+        self.state.ready_state.get()
+    }
+
+    pub(crate) fn url(&self) -> String {
+        self.state.url.clone()
+    }
+
+    /// Closes the connection, which is not opened again.
+    pub(crate) fn close(&self) {
+        self.state.ready_state.set(EventSource::CLOSED);
+        self.state.abort.abort();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.ready_state() == EventSource::CLOSED
+    }
+
+    fn dispatch(&self, event: &Event) {
+        if let Err(err) = self.target.dispatch_event(event) {
+            leptos::logging::error!("Failed to dispatch SSE event: {:?}", err);
+        }
+    }
+
+    fn dispatch_error(&self) {
+        match Event::new("error") {
+            Ok(event) => self.dispatch(&event),
+            Err(err) => leptos::logging::error!("Failed to create SSE error event: {:?}", err),
+        }
+    }
+
+    async fn run(self) {
+        let mut parser = Parser::default();
+        loop {
+            let result = self.connect(&mut parser).await;
+            if self.is_closed() {
+                return;
+            }
+            match result {
+                Ok(()) => {}
+                Err(Failure::Network(err)) => {
+                    leptos::logging::warn!(
+                        "SSE connection to {} was lost: {:?}",
+                        self.state.url,
+                        err
+                    );
+                }
+                Err(Failure::Rejected(reason)) => {
+                    leptos::logging::error!(
+                        "SSE connection to {} failed: {}",
+                        self.state.url,
+                        reason
+                    );
+                    self.state.ready_state.set(EventSource::CLOSED);
+                    self.dispatch_error();
+                    return;
+                }
+            }
+            self.state.ready_state.set(EventSource::CONNECTING);
+            self.dispatch_error();
+            sleep(parser.retry.unwrap_or(DEFAULT_RETRY)).await;
+            // The error handlers may have closed the connection, or it was closed while waiting
+            if self.is_closed() {
+                return;
+            }
+        }
+    }
+
+    /// Opens the stream, and dispatches its events until it ends.
+    async fn connect(&self, parser: &mut Parser) -> Result<(), Failure> {
+        let window = web_sys::window()
+            .ok_or_else(|| Failure::Rejected("no window to fetch from".to_owned()))?;
+        let headers = Headers::new()?;
+        for (name, value) in &self.state.headers {
+            // Sending the request again would not fix an invalid header
+            headers
+                .set(name, value)
+                .map_err(|err| Failure::Rejected(format!("invalid header {name}: {err:?}")))?;
+        }
+        headers.set("Accept", "text/event-stream")?;
+        if !parser.last_event_id.is_empty() {
+            headers.set("Last-Event-ID", &parser.last_event_id)?;
+        }
+        let init = RequestInit::new();
+        init.set_method("GET");
+        init.set_headers(&headers);
+        init.set_signal(Some(&self.state.abort.signal()));
+        init.set_credentials(if self.state.with_credentials {
+            RequestCredentials::Include
+        } else {
+            RequestCredentials::SameOrigin
+        });
+
+        let response: Response =
+            JsFuture::from(window.fetch_with_str_and_init(&self.state.url, &init))
+                .await?
+                .dyn_into()?;
+        if !response.ok() {
+            return Err(Failure::Rejected(format!(
+                "the server responded with status {}",
+                response.status()
+            )));
+        }
+        let content_type = response.headers().get("Content-Type")?.unwrap_or_default();
+        if !content_type.starts_with("text/event-stream") {
+            return Err(Failure::Rejected(format!(
+                "the server responded with content type {content_type:?} instead of an event stream"
+            )));
+        }
+        let Some(body) = response.body() else {
+            return Err(Failure::Rejected(
+                "the server responded without a body".to_owned(),
+            ));
+        };
+        // Without options, `getReader` returns a default reader
+        let reader: ReadableStreamDefaultReader = body.get_reader().unchecked_into();
+
+        self.state.ready_state.set(EventSource::OPEN);
+        self.dispatch(&Event::new("open")?);
+
+        parser.reset();
+        let decoder = TextDecoder::new()?;
+        let options = TextDecodeOptions::new();
+        // Characters split across chunks are kept by the decoder until the next chunk
+        options.set_stream(true);
+        loop {
+            let result: ReadableStreamReadResult =
+                JsFuture::from(reader.read()).await?.unchecked_into();
+            if result.get_done().unwrap_or(false) {
+                return Ok(());
+            }
+            let chunk: Uint8Array = result.get_value().dyn_into()?;
+            let text = decoder.decode_with_js_u8_array_and_options(&chunk, &options)?;
+            for message in parser.feed(&text) {
+                // The handler of a previous message may have closed the connection
+                if self.is_closed() {
+                    return Ok(());
+                }
+                let init = MessageEventInit::new();
+                init.set_data(&JsValue::from_str(&message.data));
+                init.set_last_event_id(&message.last_event_id);
+                let event = MessageEvent::new_with_event_init_dict(&message.event_type, &init)?;
+                self.dispatch(&event);
+            }
+        }
+    }
+}
+
+impl Deref for FetchEventSource {
+    type Target = EventTarget;
+
+    fn deref(&self) -> &Self::Target {
+        &self.target
+    }
+}
+
+/// Waits for `millis` milliseconds.
+async fn sleep(millis: u32) {
+    let promise = Promise::new(&mut |resolve, _| {
+        if let Some(window) = web_sys::window() {
+            let millis = i32::try_from(millis).unwrap_or(i32::MAX);
+            if let Err(err) =
+                window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis)
+            {
+                leptos::logging::error!("Failed to wait before reconnecting: {:?}", err);
+            }
+        }
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+/// A message parsed from an event stream.
+struct Message {
+    event_type: String,
+    data: String,
+    last_event_id: String,
+}
+
+/// Parses an event stream, as specified by the HTML standard.
+#[derive(Default)]
+struct Parser {
+    // The start of a line which continues in the next chunk
+    line: String,
+    // Set when a chunk ends with `\r`, which may be followed by `\n` in the next chunk
+    pending_cr: bool,
+    event_type: String,
+    data: String,
+    // Kept across connections, and sent when reconnecting
+    last_event_id: String,
+    retry: Option<u32>,
+}
+
+impl Parser {
+    /// Drops the incomplete message of a previous connection.
+    fn reset(&mut self) {
+        self.line.clear();
+        self.pending_cr = false;
+        self.event_type.clear();
+        self.data.clear();
+    }
+
+    /// Parses the next chunk of the stream, returning the messages it completes.
+    fn feed(&mut self, chunk: &str) -> Vec<Message> {
+        let mut messages = Vec::new();
+        let mut rest = chunk;
+        if std::mem::take(&mut self.pending_cr) {
+            rest = rest.strip_prefix('\n').unwrap_or(rest);
+        }
+        while let Some(end) = rest.find(['\r', '\n']) {
+            self.line.push_str(&rest[..end]);
+            let newline = rest.as_bytes()[end];
+            rest = &rest[end + 1..];
+            if newline == b'\r' {
+                if rest.is_empty() {
+                    self.pending_cr = true;
+                } else {
+                    rest = rest.strip_prefix('\n').unwrap_or(rest);
+                }
+            }
+            let line = std::mem::take(&mut self.line);
+            messages.extend(self.process_line(&line));
+        }
+        self.line.push_str(rest);
+        messages
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<Message> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event_type = value.to_owned(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = value.to_owned(),
+            "retry" if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) => {
+                if let Ok(retry) = value.parse() {
+                    self.retry = Some(retry);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<Message> {
+        let event_type = std::mem::take(&mut self.event_type);
+        let mut data = std::mem::take(&mut self.data);
+        if data.is_empty() {
+            return None;
+        }
+        data.pop();
+        Some(Message {
+            event_type: if event_type.is_empty() {
+                "message".to_owned()
+            } else {
+                event_type
+            },
+            data,
+            last_event_id: self.last_event_id.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `chunks` to a new parser, returning the event type, data and id of the messages.
+    fn parse(chunks: &[&str]) -> Vec<(String, String, String)> {
+        let mut parser = Parser::default();
+        chunks
+            .iter()
+            .flat_map(|chunk| parser.feed(chunk))
+            .map(|message| (message.event_type, message.data, message.last_event_id))
+            .collect()
+    }
+
+    fn message(event_type: &str, data: &str, id: &str) -> (String, String, String) {
+        (event_type.to_owned(), data.to_owned(), id.to_owned())
+    }
+
+    #[test]
+    fn joins_data_lines() {
+        assert_eq!(
+            parse(&["data: first\ndata:second\n\n"]),
+            [message("message", "first\nsecond", "")]
+        );
+    }
+
+    #[test]
+    fn splits_lines_on_any_newline() {
+        assert_eq!(
+            parse(&["data: a\r\n\r\ndata: b\r\rdata: c\n\n"]),
+            [
+                message("message", "a", ""),
+                message("message", "b", ""),
+                message("message", "c", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn joins_crlf_split_across_chunks() {
+        // The `\n` ends the same line as the `\r`, rather than an empty one
+        assert_eq!(
+            parse(&["data: a\r", "\ndata: b\r", "\n\r", "\n"]),
+            [message("message", "a\nb", "")]
+        );
+        assert_eq!(
+            parse(&["data: a", "b\n", "\n"]),
+            [message("message", "ab", "")]
+        );
+    }
+
+    #[test]
+    fn ignores_comments() {
+        assert_eq!(
+            parse(&[": heartbeat\n\nevent: counter\n:comment\ndata: 1\n\n"]),
+            [message("counter", "1", "")]
+        );
+    }
+
+    #[test]
+    fn keeps_the_last_event_id() {
+        assert_eq!(
+            parse(&["id: 1\ndata: a\n\n", "data: b\n\n", "id\ndata: c\n\n"]),
+            [
+                message("message", "a", "1"),
+                message("message", "b", "1"),
+                message("message", "c", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn dispatches_nothing_without_data() {
+        assert_eq!(
+            parse(&["event: counter\nid: 1\n\n", "data: a\n\n"]),
+            [
+                // The event type is reset, but the id is kept
+                message("message", "a", "1"),
+            ]
+        );
+        assert_eq!(parse(&["data\n\n"]), [message("message", "", "")]);
+    }
+
+    #[test]
+    fn parses_the_retry_field() {
+        let mut parser = Parser::default();
+        parser.feed("retry: 1000\nretry: soon\n\n");
+        assert_eq!(parser.retry, Some(1000));
+    }
+}
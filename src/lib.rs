@@ -2,34 +2,252 @@
 #![doc = include_str!("../README.md")]
 
 use std::borrow::Cow;
+use std::rc::Rc;
+use std::time::Duration;
 
 use json_patch::Patch;
-use leptos::{create_signal, ReadSignal};
+use leptos::{create_memo, create_signal, Memo, ReadSignal, SignalWith, WriteSignal};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use wasm_bindgen::JsValue;
+use web_sys::EventSourceInit;
+
+mod codec;
+mod resync;
+pub use crate::codec::{Codec, CodecError, SseCodec};
+pub use crate::resync::{
+    register_sse_snapshot, sse_snapshot, unregister_sse_snapshot, SseSnapshot,
+};
+
+cfg_if::cfg_if! {
+    if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+        mod fetch;
+    }
+}
+
+cfg_if::cfg_if! {
+    // Only the client applies patches
+    if #[cfg(all(feature = "in-place-patch", any(target_arch = "wasm32", feature = "testing")))] {
+        mod patch;
+        use crate::patch::{patch as apply_patch, PatchError};
+    } else if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+        use json_patch::{patch as apply_patch, PatchError};
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "testing")] {
+        mod testing;
+        pub use crate::testing::MockSse;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "ssr", any(feature = "actix", feature = "axum", feature = "warp", feature = "poem", feature = "rocket", feature = "tide", feature = "tower")))] {
+        mod server;
+        pub use crate::server::{
+            sse_headers, Backend, BroadcastSender, ChannelReceiver, ConnectionContext, EventMeta,
+            LossySender, Multiplex, RetryBackend, ShutdownToken, SignalState, TimerBackend,
+            UnboundedSender, UpdateMetrics,
+        };
+        pub use tokio::sync::mpsc::error::{SendError, TrySendError};
+    }
+}
 
 cfg_if::cfg_if! {
     if #[cfg(all(feature = "actix", feature = "ssr"))] {
-        mod actix;
-        pub use crate::actix::*;
+        pub mod actix;
     }
 }
 
 cfg_if::cfg_if! {
     if #[cfg(all(feature = "axum", feature = "ssr"))] {
-        mod axum;
-        pub use crate::axum::*;
+        pub mod axum;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "warp", feature = "ssr"))] {
+        pub mod warp;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "poem", feature = "ssr"))] {
+        pub mod poem;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "rocket", feature = "ssr"))] {
+        pub mod rocket;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "tide", feature = "ssr"))] {
+        pub mod tide;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "tower", feature = "ssr"))] {
+        pub mod tower;
+    }
+}
+
+// With a single backend, the root has its types as before they were shared, so the backend of
+// `leptos_sse::ServerSentEvents::channel` and the like is inferred
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "ssr", feature = "actix", not(any(feature = "axum", feature = "warp", feature = "poem", feature = "rocket", feature = "tide", feature = "tower"))))] {
+        pub use crate::actix::{BroadcastChannelStream, BroadcastSubscriber, ChannelStream, Sender, ServerSentEvents};
+    } else if #[cfg(all(feature = "ssr", feature = "axum", not(any(feature = "actix", feature = "warp", feature = "poem", feature = "rocket", feature = "tide", feature = "tower"))))] {
+        pub use crate::axum::{BroadcastChannelStream, BroadcastSubscriber, ChannelStream, Sender, ServerSentEvents};
+    } else if #[cfg(all(feature = "ssr", feature = "warp", not(any(feature = "actix", feature = "axum", feature = "poem", feature = "rocket", feature = "tide", feature = "tower"))))] {
+        pub use crate::warp::{BroadcastChannelStream, BroadcastSubscriber, ChannelStream, Sender, ServerSentEvents};
+    } else if #[cfg(all(feature = "ssr", feature = "poem", not(any(feature = "actix", feature = "axum", feature = "warp", feature = "rocket", feature = "tide", feature = "tower"))))] {
+        pub use crate::poem::{BroadcastChannelStream, BroadcastSubscriber, ChannelStream, Sender, ServerSentEvents};
+    } else if #[cfg(all(feature = "ssr", feature = "rocket", not(any(feature = "actix", feature = "axum", feature = "warp", feature = "poem", feature = "tide", feature = "tower"))))] {
+        pub use crate::rocket::{BroadcastChannelStream, BroadcastSubscriber, ChannelStream, Sender, ServerSentEvents};
+    } else if #[cfg(all(feature = "ssr", feature = "tide", not(any(feature = "actix", feature = "axum", feature = "warp", feature = "poem", feature = "rocket", feature = "tower"))))] {
+        pub use crate::tide::{BroadcastChannelStream, BroadcastSubscriber, ChannelStream, Sender, ServerSentEvents};
+    } else if #[cfg(all(feature = "ssr", feature = "tower", not(any(feature = "actix", feature = "axum", feature = "warp", feature = "poem", feature = "rocket", feature = "tide"))))] {
+        pub use crate::tower::{BroadcastChannelStream, BroadcastSubscriber, ChannelStream, Sender, ServerSentEvents};
+    } else if #[cfg(all(feature = "ssr", any(feature = "actix", feature = "axum", feature = "warp", feature = "poem", feature = "rocket", feature = "tide", feature = "tower")))] {
+        pub use crate::generic::{BroadcastChannelStream, BroadcastSubscriber, ChannelStream, Sender, ServerSentEvents};
+    }
+}
+
+/// The types shared by every web framework, generic over its [`Backend`].
+///
+/// The module of each framework names them for its backend, such as
+/// `leptos_sse::axum::ServerSentEvents`.
+#[cfg(all(
+    feature = "ssr",
+    any(
+        feature = "actix",
+        feature = "axum",
+        feature = "warp",
+        feature = "poem",
+        feature = "rocket",
+        feature = "tide",
+        feature = "tower"
+    )
+))]
+pub mod generic {
+    pub use crate::server::{
+        BroadcastChannelStream, BroadcastSubscriber, ChannelStream, Sender, ServerSentEvents,
+    };
+}
+
+/// A type synced as a server signal, under a name known at compile time.
+///
+/// This ties the name used by the server and the client to the type, rather than repeating it
+/// as a string on both sides. With the `derive` feature, it can be derived, naming the signal
+/// after the type unless a name is given with `#[sse_signal(name = "...")]`.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "derive")]
+/// # {
+/// # use serde::{Deserialize, Serialize};
+/// use leptos_sse::SseSignal;
+///
+/// #[derive(Clone, Default, Serialize, Deserialize, SseSignal)]
+/// #[sse_signal(name = "counter")]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// assert_eq!(Count::NAME, "counter");
+/// # }
+/// ```
+pub trait SseSignal {
+    /// The name of the signal, and of the SSE events updating it.
+    const NAME: &'static str;
+}
+
+#[cfg(feature = "derive")]
+pub use leptos_sse_macros::{ServerSignal, SseSignal};
+
+/// Used by the code generated by `#[derive(ServerSignal)]`.
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod __private {
+    pub use leptos::ReadSignal;
+    pub use serde::de::DeserializeOwned;
+    pub use serde::Serialize;
+    pub use serde_json;
+
+    #[cfg(all(
+        feature = "ssr",
+        any(
+            feature = "actix",
+            feature = "axum",
+            feature = "warp",
+            feature = "poem",
+            feature = "rocket",
+            feature = "tide",
+            feature = "tower"
+        )
+    ))]
+    pub use crate::server::{ChannelStream, Sender, ServerSentEvents};
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "ssr", any(feature = "actix", feature = "axum", feature = "warp", feature = "poem", feature = "rocket", feature = "tide", feature = "tower")))] {
+        /// Generates `sse_channel` for `#[derive(ServerSignal)]`, on the server only.
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! __sse_channel {
+            () => {
+                /// Creates the channel updating the server signal with the web framework `B`,
+                /// such as `leptos_sse::axum::Axum`, see `ServerSentEvents::channel_for`.
+                pub fn sse_channel<B: $crate::Backend>(
+                    buffer: usize,
+                ) -> ::core::result::Result<
+                    (
+                        $crate::__private::Sender<Self, B>,
+                        $crate::__private::ServerSentEvents<$crate::__private::ChannelStream<Self, B>, B>,
+                    ),
+                    $crate::__private::serde_json::Error,
+                >
+                where
+                    Self: ::core::default::Default + $crate::__private::Serialize,
+                {
+                    $crate::__private::ServerSentEvents::<$crate::__private::ChannelStream<Self, B>, B>::channel_for(buffer)
+                }
+            };
+        }
+    } else {
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! __sse_channel {
+            () => {};
+        }
     }
 }
 
 /// A server signal update containing the signal type name and json patch.
 ///
 /// This is whats sent over the SSE, and is used to patch the signal.
+/// A snapshot update instead replaces the whole value of the signal.
+///
+/// Updates sent by `ServerSentEvents` carry a version, which the client uses to detect
+/// missed updates.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ServerSignalUpdate {
     name: Cow<'static, str>,
     patch: Patch,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snapshot: Option<Value>,
+    #[serde(default, skip_serializing_if = "is_unversioned")]
+    version: u64,
+}
+
+fn is_unversioned(version: &u64) -> bool {
+    *version == 0
 }
 
 impl ServerSignalUpdate {
@@ -48,6 +266,8 @@ impl ServerSignalUpdate {
         Ok(ServerSignalUpdate {
             name: name.into(),
             patch,
+            snapshot: None,
+            version: 0,
         })
     }
 
@@ -57,191 +277,3133 @@ impl ServerSignalUpdate {
         ServerSignalUpdate {
             name: name.into(),
             patch,
+            snapshot: None,
+            version: 0,
+        }
+    }
+
+    /// Creates a new snapshot [`ServerSignalUpdate`], replacing the signal with `value`.
+    ///
+    /// Unlike a patch, a snapshot applies regardless of the current value of the signal on the
+    /// client, so it can be used to recover a client which missed some updates.
+    pub fn snapshot<T>(
+        name: impl Into<Cow<'static, str>>,
+        value: &T,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        Ok(ServerSignalUpdate {
+            name: name.into(),
+            patch: Patch(Vec::new()),
+            snapshot: Some(serde_json::to_value(value)?),
+            version: 0,
+        })
+    }
+
+    /// Creates a new [`ServerSignalUpdate`] applying `patch` to the signal `name`.
+    ///
+    /// The patch is sent as it is, so it must apply to the value the client holds.
+    pub fn from_parts(name: impl Into<Cow<'static, str>>, patch: Patch) -> Self {
+        ServerSignalUpdate {
+            name: name.into(),
+            patch,
+            snapshot: None,
+            version: 0,
+        }
+    }
+
+    /// The name of the signal the update applies to.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The json patch of the update, which is empty for a snapshot.
+    pub fn patch(&self) -> &Patch {
+        &self.patch
+    }
+
+    /// The value replacing the signal, if the update is a snapshot.
+    pub fn snapshot_value(&self) -> Option<&Value> {
+        self.snapshot.as_ref()
+    }
+
+    /// The position of the update in the stream of its signal, starting at 1.
+    ///
+    /// This is 0 for an update which was not sent by a `ServerSentEvents`, which the client
+    /// applies without checking for missed updates.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Applies the update to `doc`, either by patching or replacing it.
+    #[cfg(any(target_arch = "wasm32", feature = "testing"))]
+    pub(crate) fn apply(&self, doc: &mut Value) -> Result<(), PatchError> {
+        match &self.snapshot {
+            Some(snapshot) => {
+                *doc = snapshot.clone();
+                Ok(())
+            }
+            None => apply_patch(doc, &self.patch),
+        }
+    }
+}
+
+/// How the server turns a new value of a signal into a [`ServerSignalUpdate`].
+///
+/// Patches are usually much smaller than the value, but not always: replacing most elements of
+/// an array produces an operation per element. The other strategies fall back to a snapshot
+/// of the whole value in such cases.
+///
+/// Every strategy sends a snapshot when the root of the value changes shape, such as an enum
+/// switching variants: either its json type changes, or it is an object whose only key
+/// changes, as for the variants of an externally tagged enum. Enums with other
+/// representations, such as `#[serde(tag = "kind")]`, are diffed field by field, so prefer
+/// the default representation, or [`DiffStrategy::Snapshot`], for values which are sum types.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DiffStrategy {
+    /// Always send a json patch.
+    #[default]
+    Patch,
+    /// Send a json patch, or a snapshot when the patch has more than `max_operations`.
+    Bounded {
+        /// The largest number of operations sent as a patch.
+        max_operations: usize,
+    },
+    /// Send whichever of the json patch or the snapshot is smaller once serialized.
+    Smallest,
+    /// Always send a snapshot, for values which change as a whole.
+    Snapshot,
+}
+
+/// What the server does with a value of a signal which fails to serialize, for example a map
+/// with keys json cannot represent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SerializeErrorPolicy {
+    /// End the stream with the error.
+    #[default]
+    Fail,
+    /// Skip the value and keep the stream going. The error is sent to the client in its
+    /// place, as for the errors of the stream, see [`sse_last_error`].
+    Skip,
+}
+
+/// Several [`ServerSignalUpdate`]s sent together in a single SSE.
+///
+/// The client applies all of them at once, so anything depending on several of the signals
+/// reruns once rather than after each update. Send it as the json data of an unnamed event.
+///
+/// # Example
+///
+/// ```
+/// # use leptos_sse::{ServerSignalBatch, ServerSignalUpdate};
+/// # let (old_count, new_count, old_total, new_total) = (0, 1, 10, 11);
+/// let batch = ServerSignalBatch::new([
+///     ServerSignalUpdate::new("count", &old_count, &new_count)?,
+///     ServerSignalUpdate::new("total", &old_total, &new_total)?,
+/// ]);
+/// let data = serde_json::to_string(&batch)?;
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerSignalBatch {
+    updates: Vec<ServerSignalUpdate>,
+}
+
+impl ServerSignalBatch {
+    /// Creates a new [`ServerSignalBatch`], applied by the client in order.
+    pub fn new(updates: impl IntoIterator<Item = ServerSignalUpdate>) -> Self {
+        ServerSignalBatch {
+            updates: updates.into_iter().collect(),
+        }
+    }
+
+    /// Adds an update to the batch.
+    pub fn push(&mut self, update: ServerSignalUpdate) {
+        self.updates.push(update);
+    }
+
+    /// Returns the updates of the batch.
+    pub fn updates(&self) -> &[ServerSignalUpdate] {
+        &self.updates
+    }
+}
+
+/// An error reported by the server for a signal, after which the signal stopped updating.
+///
+/// This is sent over the SSE as an `error` event when the server stream of the signal fails.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerSignalError {
+    name: Cow<'static, str>,
+    message: String,
+}
+
+impl ServerSignalError {
+    /// Creates a new [`ServerSignalError`] for the signal `name`.
+    pub fn new(name: impl Into<Cow<'static, str>>, message: impl Into<String>) -> Self {
+        ServerSignalError {
+            name: name.into(),
+            message: message.into(),
+        }
+    }
+
+    /// The name of the signal the error occurred for.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The error message reported by the server.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for ServerSignalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "server signal {} failed: {}", self.name, self.message)
+    }
+}
+
+impl std::error::Error for ServerSignalError {}
+
+/// What failed when the client applied an update to a server signal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AppliedPatchErrorKind {
+    /// The json patch did not apply to the value of the signal, which was left untouched.
+    Patch,
+    /// The patched value did not deserialize into the type of the signal.
+    Deserialize,
+}
+
+/// An update the client failed to apply to a server signal, see [`sse_error_log`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppliedPatchError {
+    name: Cow<'static, str>,
+    patch: Patch,
+    kind: AppliedPatchErrorKind,
+    message: String,
+}
+
+impl AppliedPatchError {
+    /// The name of the signal the update was for.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The patch which failed to apply.
+    ///
+    /// When several updates were applied at once, this holds the operations of all of them.
+    pub fn patch(&self) -> &Patch {
+        &self.patch
+    }
+
+    /// What failed.
+    pub fn kind(&self) -> AppliedPatchErrorKind {
+        self.kind
+    }
+
+    /// The error of json-patch or serde.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for AppliedPatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            AppliedPatchErrorKind::Patch => write!(
+                f,
+                "failed to apply update to {}: {}",
+                self.name, self.message
+            ),
+            AppliedPatchErrorKind::Deserialize => {
+                write!(
+                    f,
+                    "failed to deserialize server signal {}: {}",
+                    self.name, self.message
+                )
+            }
         }
     }
 }
 
+impl std::error::Error for AppliedPatchError {}
+
+/// The state of the server signal SSE connection.
+///
+/// This mirrors the `readyState` of the underlying `EventSource`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    /// The connection has not yet been established, or was lost and the browser is reconnecting.
+    #[default]
+    Connecting,
+    /// The connection is open and dispatching events.
+    Open,
+    /// The connection is closed and the browser will not reconnect.
+    ///
+    /// This is also the case once the server ended the stream with a `close` event.
+    Closed,
+}
+
+/// How the client connects to the SSE url.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Transport {
+    /// The `EventSource` of the browser.
+    #[default]
+    EventSource,
+    /// A stream read through `fetch`, parsed and reconnected like an `EventSource`.
+    ///
+    /// Unlike an `EventSource`, it reports a response which is not a successful event stream
+    /// as an error, and closes the connection instead of retrying. The `ServerSignalEventSource`
+    /// context is not provided for it.
+    Fetch,
+}
+
+/// When the client applies the updates it receives.
+///
+/// Each update sets its signal, which runs the effects depending on it. Applying the updates of
+/// several events together, in a single [`batch`](leptos::batch), runs those effects once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum UpdateBatching {
+    /// Each event is applied as soon as it is received.
+    ///
+    /// The updates of a [`ServerSignalBatch`] are still applied together.
+    #[default]
+    Immediate,
+    /// The events received until the next repaint are applied together, right before it.
+    AnimationFrame,
+    /// The events received within the duration after the first one are applied together.
+    Window(Duration),
+}
+
+/// How the client reconnects when the SSE connection is lost, see [`SseOptions::with_reconnect`].
+///
+/// The delay before the `n`th attempt in a row is `base_delay * 2^n`, up to `max_delay`, minus a
+/// random part of up to `jitter` of it. The jitter spreads the reconnections of clients which
+/// lost their connection at the same time, so they do not overwhelm the server together.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReconnectPolicy {
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+}
+
+impl ReconnectPolicy {
+    /// Creates a policy waiting `base_delay` before the first attempt, and doubling the delay
+    /// after each failed attempt up to `max_delay`, with a jitter of `0.5`.
+    pub fn new(base_delay: Duration, max_delay: Duration) -> Self {
+        ReconnectPolicy {
+            base_delay,
+            max_delay,
+            jitter: 0.5,
+        }
+    }
+
+    /// Sets the largest part of the delay which is randomly removed, between `0.0` and `1.0`.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The delay before the first attempt.
+    pub fn base_delay(&self) -> Duration {
+        self.base_delay
+    }
+
+    /// The longest delay between two attempts.
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    /// The largest part of the delay which is randomly removed.
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    /// The delay before the attempt following `attempt` failed ones, given a `random` number
+    /// between `0.0` and `1.0`.
+    #[cfg(any(target_arch = "wasm32", feature = "testing"))]
+    pub(crate) fn delay(&self, attempt: u32, random: f64) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        backoff.mul_f64(1.0 - self.jitter * random)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    /// Starts at 1 second, up to 30 seconds.
+    fn default() -> Self {
+        ReconnectPolicy::new(Duration::from_secs(1), Duration::from_secs(30))
+    }
+}
+
 /// Provides a SSE url for server signals, if there is not already one provided.
 /// This ensures that you can provide it at the highest possible level, without overwriting a SSE
 /// that has already been provided (for example, by a server-rendering integration.)
+/// A connection that was closed with [`close_sse`] is replaced by a new one.
 ///
 /// Note, the server should have a route to handle this SSE.
 ///
+/// The url may be relative, such as `/sse`, in which case it is resolved against the url of the
+/// current page, so the app connects to the server it was served from in every environment. The
+/// same goes for the urls given to [`provide_sse_with`] and to [`reconnect_sse`].
+///
+/// When rendering on the server, this connects to nothing and returns `Ok(())`, so server
+/// signals keep their initial value until the app hydrates in the browser. See
+/// [`sse_is_client`] and [`sse_is_noop`] to tell these cases apart. With the `testing` feature,
+/// it provides a [`MockSse`](crate::MockSse) instead, which native tests can push updates into.
+///
 /// # Example
 ///
-/// ```ignore
+/// ```no_run
+/// # use leptos::*;
 /// #[component]
 /// pub fn App() -> impl IntoView {
-///     // Provide SSE connection
-///     leptos_sse::provide_sse("http://localhost:3000/sse").unwrap();
+///     // Provide SSE connection to the server the app was served from
+///     leptos_sse::provide_sse("/sse").unwrap();
 ///
 ///     // ...
+/// #   ()
 /// }
 /// ```
-#[allow(unused_variables)]
 pub fn provide_sse(url: &str) -> Result<(), JsValue> {
-    provide_sse_inner(url)
+    provide_sse_inner(url, SseOptions::default())
 }
 
-/// Creates a signal which is controlled by the server.
+/// Whether the app runs in the browser, where [`provide_sse`] connects to the server.
 ///
-/// This signal is initialized as T::default, is read-only on the client, and is updated through json patches
-/// sent through a SSE connection.
+/// This is `false` when rendering on the server, where server signals only ever have their
+/// initial value, for example to render a placeholder rather than a value which is not live
+/// yet.
 ///
 /// # Example
 ///
-/// ```
-/// #[derive(Clone, Default, Serialize, Deserialize)]
-/// pub struct Count {
-///     pub value: i32,
-/// }
-///
+/// ```no_run
+/// # use leptos::*;
+/// # use leptos_sse::create_sse_signal;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
 /// #[component]
-/// pub fn App() -> impl IntoView {
-///     // Create server signal
+/// pub fn Counter() -> impl IntoView {
 ///     let count = create_sse_signal::<Count>("counter");
 ///
 ///     view! {
-///         <h1>"Count: " {move || count().value.to_string()}</h1>
+///         <Show when=leptos_sse::sse_is_client fallback=|| view! { <p>"Loading..."</p> }>
+///             <h1>"Count: " {move || count.get().value.to_string()}</h1>
+///         </Show>
 ///     }
 /// }
 /// ```
-#[allow(unused_variables)]
-pub fn create_sse_signal<T>(name: impl Into<Cow<'static, str>>) -> ReadSignal<T>
-where
-    T: Default + Serialize + for<'de> Deserialize<'de>,
-{
-    let name = name.into();
-    let (get, set) = create_signal(T::default());
+pub fn sse_is_client() -> bool {
+    cfg!(target_arch = "wasm32")
+}
 
-    cfg_if::cfg_if! {
-        if #[cfg(target_arch = "wasm32")] {
-            use leptos::{use_context, create_effect, create_rw_signal, SignalSet, SignalGet};
+/// Whether the SSE provided to this part of the app connects to nothing, as [`provide_sse`]
+/// does outside the browser.
+///
+/// Server signals created under it never receive updates. Unlike [`sse_is_client`], this is
+/// `false` when no SSE was provided at all, and for the [`MockSse`](crate::MockSse) of tests,
+/// which [`provide_sse`] provides outside the browser with the `testing` feature.
+pub fn sse_is_noop() -> bool {
+    leptos::use_context::<NoopSse>().is_some()
+}
 
-            let signal = create_rw_signal(serde_json::to_value(T::default()).unwrap());
-            if let Some(ServerSignalEventSourceContext { state_signals, .. }) = use_context::<ServerSignalEventSourceContext>() {
-                let name: Cow<'static, str> = name.into();
-                state_signals.borrow_mut().insert(name.clone(), signal);
+/// Provided by [`provide_sse`] in place of a connection outside the browser.
+#[derive(Clone, Copy, Debug)]
+struct NoopSse;
 
-                // Note: The leptos docs advise against doing this. It seems to work
-                // well in testing, and the primary caveats are around unnecessary
-                // updates firing, but our state synchronization already prevents
-                // that on the server side
-                create_effect(move |_| {
-                    let name = name.clone();
-                    let new_value = serde_json::from_value(signal.get()).unwrap();
-                    set.set(new_value);
-                });
+/// Provides a SSE url for server signals, connecting to it and applying the updates it receives
+/// as set by `options`.
+///
+/// This is like [`provide_sse`], which uses the default [`SseOptions`]. If a connection is
+/// already provided, it is kept, and only the way its updates are applied changes.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # fn token() -> String {
+/// #     String::new()
+/// # }
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Provide SSE connection, authenticated with a bearer token, and reconnecting after 1 to
+///     // 30 seconds
+///     let authorization = format!("Bearer {}", token());
+///     let options = leptos_sse::SseOptions::new()
+///         .with_headers(&[("Authorization", &authorization)])
+///         .with_reconnect(leptos_sse::ReconnectPolicy::default());
+///     leptos_sse::provide_sse_with("https://api.example.com/sse", options).unwrap();
+///
+///     // ...
+/// #   ()
+/// }
+/// ```
+pub fn provide_sse_with(url: &str, options: SseOptions) -> Result<(), JsValue> {
+    provide_sse_inner(url, options)
+}
 
-            } else {
-                leptos::logging::error!(
-                    r#"server signal was used without a SSE being provided.
+/// How [`provide_sse_with`] connects to the SSE url, and applies the updates it receives.
+///
+/// Each option defaults to what [`provide_sse`] does.
+#[derive(Clone, Default)]
+pub struct SseOptions {
+    options: Option<EventSourceInit>,
+    // Decodes every update when set, rather than detecting the codec of each
+    codec: Option<Rc<dyn SseCodec>>,
+    transport: Transport,
+    // Sent along each request, which requires the fetch transport
+    headers: Vec<(String, String)>,
+    batching: UpdateBatching,
+    // Reconnects on errors in place of the browser when set
+    reconnect: Option<ReconnectPolicy>,
+    // Carry updates like `message` events, in addition to the events named after signals
+    events: Vec<String>,
+}
 
-Ensure you call `leptos_sse::provide_sse("http://localhost:3000/sse")` at the highest level in your app."#
-                );
-            }
-        }
+impl SseOptions {
+    /// Creates the options of [`provide_sse`].
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    get
-}
+    /// Opens the connection with the given options, for example to enable `withCredentials`,
+    /// so cookies are sent along with a cross-origin SSE request.
+    pub fn with_event_source_init(mut self, options: &EventSourceInit) -> Self {
+        self.options = Some(options.clone());
+        self
+    }
 
-cfg_if::cfg_if! {
-    if #[cfg(target_arch = "wasm32")] {
-        use std::cell::RefCell;
-        use std::collections::HashMap;
-        use std::ops::{Deref, DerefMut};
-        use std::rc::Rc;
+    /// Only accept updates encoded with `codec`.
+    ///
+    /// By default the codec of each update is detected from its payload, see [`Codec`]. This
+    /// rejects updates encoded otherwise, which are logged instead of applied.
+    ///
+    /// `codec` is either a built-in [`Codec`] or a custom [`SseCodec`], which must be the codec
+    /// the server encodes the updates with, see `ServerSentEvents::with_codec`.
+    pub fn with_codec(mut self, codec: impl SseCodec) -> Self {
+        self.codec = Some(Rc::new(codec));
+        self
+    }
 
-        use web_sys::EventSource;
-        use leptos::{provide_context, RwSignal};
+    /// Connect through `transport`.
+    ///
+    /// Server signals are updated the same way with either transport, see [`Transport`].
+    pub fn with_transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
 
-        /// Provides the context for the server signal `web_sys::EventSource`.
-        ///
-        /// You can use this via `use_context::<ServerSignalEventSource>()` to
-        /// access the `EventSource` to set up additional event listeners and etc.
-        #[derive(Clone, Debug, PartialEq, Eq)]
-        pub struct ServerSignalEventSource(pub EventSource);
+    /// Send `headers` along each request, for example to authenticate with a bearer token
+    /// rather than cookies.
+    ///
+    /// The browser `EventSource` cannot send headers, so this sets the transport to
+    /// [`Transport::Fetch`]. The headers are sent again when the connection is reopened,
+    /// including by [`reconnect_sse`].
+    pub fn with_headers(mut self, headers: &[(&str, &str)]) -> Self {
+        self.headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self.transport = Transport::Fetch;
+        self
+    }
 
-        impl Deref for ServerSignalEventSource {
-            type Target = EventSource;
+    /// Apply the updates received together as set by `batching`.
+    ///
+    /// When the server sends many updates in quick succession, for example to several signals
+    /// changing together, applying them together renders their effects once rather than once
+    /// per update. See [`UpdateBatching`].
+    pub fn with_batching(mut self, batching: UpdateBatching) -> Self {
+        self.batching = batching;
+        self
+    }
 
-            fn deref(&self) -> &Self::Target {
-                &self.0
-            }
-        }
+    /// Reconnect as set by `policy` when the connection is lost.
+    ///
+    /// Rather than the browser retrying at a fixed interval, the connection is closed on error
+    /// and opened again after an increasing, randomized delay, see [`ReconnectPolicy`]. This
+    /// also retries when the browser would give up, for example because the server responded
+    /// with an error while restarting. [`sse_connection_state`] is
+    /// [`ConnectionState::Connecting`] until the connection is open again, and the handlers
+    /// registered with [`on_sse_error`] are called on each error.
+    pub fn with_reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
 
-        impl DerefMut for ServerSignalEventSource {
-            fn deref_mut(&mut self) -> &mut Self::Target {
+    /// Also receive updates through events of the given types.
+    ///
+    /// Updates are received from `message` events, which carry no event type, and from events
+    /// named after the signal they update, as sent by the server integrations. Servers which
+    /// send the updates of several signals under another event type, such as `update`, need
+    /// that type to be listened to as well. Updates and batches of updates received through
+    /// these events apply to any signal, as for `message` events.
+    pub fn with_events(mut self, events: &[&str]) -> Self {
+        self.events = events.iter().map(|event| event.to_string()).collect();
+        self
+    }
+}
+
+impl std::fmt::Debug for SseOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SseOptions")
+            .field("transport", &self.transport)
+            .field("batching", &self.batching)
+            .field("reconnect", &self.reconnect)
+            .field("events", &self.events)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Prefixes the names of the server signals created from now on in this part of the app with
+/// `namespace`, so `counter` is received as `namespace:counter`.
+///
+/// This keeps the signals of independently developed components apart when they share the SSE
+/// provided by [`provide_sse`]. Call it at the top of the component, before its server signals
+/// are created, and prefix the signals on the server with `ServerSentEvents::with_namespace`.
+/// The namespace is a context, so it applies to the children of the component but not to the
+/// rest of the app.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # use leptos_sse::create_sse_signal;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// #[component]
+/// pub fn CheckoutWidget() -> impl IntoView {
+///     leptos_sse::provide_sse_namespace("checkout");
+///
+///     // Receives the updates of `checkout:counter`
+///     let count = create_sse_signal::<Count>("counter");
+///
+///     // ...
+/// #   ()
+/// }
+/// ```
+pub fn provide_sse_namespace(namespace: impl Into<Cow<'static, str>>) {
+    leptos::provide_context(SseNamespace(namespace.into()));
+}
+
+/// The namespace provided by [`provide_sse_namespace`].
+#[derive(Clone, Debug)]
+struct SseNamespace(Cow<'static, str>);
+
+/// Prefixes `name` with the namespace provided by [`provide_sse_namespace`], if any.
+fn namespace_name(name: impl Into<Cow<'static, str>>) -> Cow<'static, str> {
+    let name = name.into();
+    match leptos::use_context::<SseNamespace>() {
+        Some(SseNamespace(namespace)) => namespaced(&namespace, &name).into(),
+        None => name,
+    }
+}
+
+/// The name `name` is sent as under `namespace`.
+pub(crate) fn namespaced(namespace: &str, name: &str) -> String {
+    format!("{namespace}:{name}")
+}
+
+/// Creates a signal which is controlled by the server.
+///
+/// This signal is initialized as T::default, is read-only on the client, and is updated through json patches
+/// sent through a SSE connection.
+///
+/// Updates received before the signal is created, for example when it is created on a route
+/// change while the connection is already open, are applied right away. The signal stops
+/// receiving updates when the reactive owner it was created in is disposed, for example when
+/// its component is unmounted.
+///
+/// In debug builds, each update is applied to a copy of the value, which must still deserialize
+/// into `T` before the signal is updated. Otherwise the update is rolled back and the error is
+/// logged, which catches a server and client whose types drifted apart. The `in-place-patch`
+/// feature patches the value itself instead, so the error is only logged.
+///
+/// Each update deserializes `T` straight from the patched json value, without copying it first,
+/// so the cost of an update grows with the size of the whole value rather than of the patch.
+/// Follow the parts of a large value which change with [`create_sse_field`] instead, which only
+/// deserializes the fields an update changed.
+///
+/// Fields the server skips with `skip_serializing_if`, and the entries of a `flatten`ed map,
+/// are removed from the json value when they vanish and added back when they reappear, so their
+/// patches apply in order as for any other field. The value must still deserialize into `T`
+/// without them: a missing `Option` field is `None`, but other skipped fields need
+/// `#[serde(default)]`, or the update is rejected as if the types drifted apart.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # use leptos_sse::create_sse_signal;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Create server signal
+///     let count = create_sse_signal::<Count>("counter");
+///
+///     view! {
+///         <h1>"Count: " {move || count.get().value.to_string()}</h1>
+///     }
+/// }
+/// ```
+pub fn create_sse_signal<T>(name: impl Into<Cow<'static, str>>) -> ReadSignal<T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de>,
+{
+    create_sse_signal_with_initial(name, T::default())
+}
+
+/// Creates a signal which is controlled by the server, named after `T`.
+///
+/// This is like [`create_sse_signal`], but takes the name from [`SseSignal`], so it cannot
+/// differ from the name used by `ServerSentEvents::for_signal` on the server.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "derive")]
+/// # mod app {
+/// # use leptos::*;
+/// # use leptos_sse::create_sse_signal_for;
+/// # use serde::{Deserialize, Serialize};
+/// use leptos_sse::SseSignal;
+///
+/// #[derive(Clone, Default, Serialize, Deserialize, SseSignal)]
+/// #[sse_signal(name = "counter")]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Create server signal
+///     let count = create_sse_signal_for::<Count>();
+///
+///     view! {
+///         <h1>"Count: " {move || count.get().value.to_string()}</h1>
+///     }
+/// }
+/// # }
+/// ```
+pub fn create_sse_signal_for<T>() -> ReadSignal<T>
+where
+    T: SseSignal + Default + Serialize + for<'de> Deserialize<'de>,
+{
+    create_sse_signal(T::NAME)
+}
+
+/// Creates a memo derived from a signal which is controlled by the server.
+///
+/// This is like [`create_sse_signal`], but only exposes `f` of the value, which is recomputed
+/// whenever the server updates the signal. As with any memo, readers are only notified when
+/// the derived value changes.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # use leptos_sse::create_sse_memo;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Create server signal, keeping only the text shown
+///     let count = create_sse_memo("counter", |count: &Count| count.value.to_string());
+///
+///     view! {
+///         <h1>"Count: " {count}</h1>
+///     }
+/// }
+/// ```
+pub fn create_sse_memo<T, U>(
+    name: impl Into<Cow<'static, str>>,
+    f: impl Fn(&T) -> U + 'static,
+) -> Memo<U>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de> + 'static,
+    U: PartialEq + 'static,
+{
+    let signal = create_sse_signal::<T>(name);
+    create_memo(move |_| signal.with(&f))
+}
+
+/// Creates a memo of a field of a signal which is controlled by the server, deserializing only
+/// that field.
+///
+/// `pointer` is the json pointer of the field in the value of the signal, such as `/title` or
+/// `/items/0`. Unlike [`create_sse_memo`], which derives from the whole value once it is
+/// deserialized, the field is only deserialized when an update changed it, and readers are
+/// only notified then. A large value which changes in small parts is cheap to follow this way.
+/// The memo is `None` while the field is missing, or does not deserialize into `F`.
+///
+/// The field is read from the json value of the server signal `name`, which is shared with the
+/// signals created with [`create_sse_signal`] or one of its variants under the same name, before
+/// or after the field. Without one, the json value is patched on its own, starting from
+/// `T::default()` as [`create_sse_signal`] does, and is never deserialized whole.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # use leptos_sse::create_sse_field;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Document {
+/// #     pub title: String,
+/// #     pub body: String,
+/// # }
+/// #[component]
+/// pub fn Title() -> impl IntoView {
+///     // Follow the title of a large document, without deserializing its body on each update
+///     let title = create_sse_field::<Document, String>("document", "/title");
+///
+///     view! {
+///         <h1>{move || title.get().unwrap_or_default()}</h1>
+///     }
+/// }
+/// ```
+pub fn create_sse_field<T, F>(
+    name: impl Into<Cow<'static, str>>,
+    pointer: impl Into<String>,
+) -> Memo<Option<F>>
+where
+    T: Default + Serialize,
+    F: PartialEq + for<'de> Deserialize<'de> + 'static,
+{
+    let name = namespace_name(name);
+    let pointer = pointer.into();
+    let json = match serde_json::to_value(T::default()) {
+        Ok(initial) => {
+            cfg_if::cfg_if! {
+                if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+                    use leptos::use_context;
+
+                    match use_context::<ServerSignalEventSourceContext>() {
+                        // Nothing is deserialized whole, as the setter only stands in for a signal
+                        Some(context) => register_json_signal(&context, name, initial, Rc::new(|_| Ok(()))).read_only(),
+                        None => {
+                            NoSseProvidedError.log();
+                            create_signal(initial).0
+                        }
+                    }
+                } else {
+                    create_signal(initial).0
+                }
+            }
+        }
+        Err(err) => {
+            leptos::logging::error!(
+                "Server signal {} is not updated, as its value does not serialize to json: {}",
+                name,
+                err
+            );
+            create_signal(Value::Null).0
+        }
+    };
+    // Only the field is compared, so the second memo only runs once the field changed
+    let field = create_memo(move |_| json.with(|doc| doc.pointer(&pointer).cloned()));
+    create_memo(move |_| {
+        field.with(|field| field.as_ref().and_then(|field| F::deserialize(field).ok()))
+    })
+}
+
+/// Creates a signal which is controlled by the server, starting from `initial`.
+///
+/// This is like [`create_sse_signal`], but for types where `T::default` is not a meaningful
+/// starting point.
+///
+/// The server must diff its first update against the same initial value, for example by using
+/// `ServerSentEvents::new_with_initial`, otherwise the first patch will not apply.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # use leptos_sse::create_sse_signal_with_initial;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(Clone, Serialize, Deserialize)]
+/// pub struct Clock {
+///     pub started_at: u64,
+/// }
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Create server signal
+///     let clock = create_sse_signal_with_initial("clock", Clock { started_at: 1_700_000_000 });
+///
+///     view! {
+///         <h1>"Started at: " {move || clock.get().started_at.to_string()}</h1>
+///     }
+/// }
+/// ```
+pub fn create_sse_signal_with_initial<T>(
+    name: impl Into<Cow<'static, str>>,
+    initial: T,
+) -> ReadSignal<T>
+where
+    T: Serialize + for<'de> Deserialize<'de>,
+{
+    let (get, set) = create_signal(initial);
+    if let Err(err) = register_sse_signal(namespace_name(name), get, set, None, None) {
+        err.log();
+    }
+
+    get
+}
+
+/// Creates a signal which is controlled by the server, set to the values decoded by `decoder`.
+///
+/// This is the client end of `ServerSentEvents::raw`: each value is sent whole as bytes, rather
+/// than as a json patch, so `T` does not need to be serializable. The signal is initialized as
+/// `T::default`. Values the server sent before the signal was created are not received, and
+/// bytes which fail to decode are logged, see [`sse_error_log`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # #[derive(Clone, Default)]
+/// # pub struct Frame;
+/// # impl Frame {
+/// #     fn decode_png(_bytes: &[u8]) -> Result<Frame, String> {
+/// #         Ok(Frame)
+/// #     }
+/// # }
+/// #[component]
+/// pub fn Camera() -> impl IntoView {
+///     let frame = leptos_sse::create_sse_signal_raw("camera", |bytes: &[u8]| {
+///         Frame::decode_png(bytes)
+///     });
+///
+///     // ...
+/// #   ()
+/// }
+/// ```
+#[cfg(feature = "raw")]
+pub fn create_sse_signal_raw<T, E>(
+    name: impl Into<Cow<'static, str>>,
+    decoder: impl Fn(&[u8]) -> Result<T, E> + 'static,
+) -> ReadSignal<T>
+where
+    T: Default + 'static,
+    E: std::fmt::Display,
+{
+    let (get, set) = create_signal(T::default());
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::{use_context, on_cleanup, SignalSet};
+
+            let Some(context) = use_context::<ServerSignalEventSourceContext>() else {
+                NoSseProvidedError.log();
+                return get;
+            };
+            let name = namespace_name(name);
+            let setter: RawSetter = Rc::new(move |bytes| {
+                let value = decoder(bytes).map_err(|err| format!("{}: {}", std::any::type_name::<T>(), err))?;
+                set.set(value);
+                Ok(())
+            });
+
+            // Values are sent as events named after the signal
+            if let Some(handler) = &*context.message_handler.borrow() {
+                listen_signal(&context.source(), &name, handler);
+            }
+            context.raw_setters.borrow_mut().insert(name.clone(), setter.clone());
+
+            // Stop setting the signal once its owner is disposed, unless the same name was
+            // registered again since
+            on_cleanup(move || {
+                let mut raw_setters = context.raw_setters.borrow_mut();
+                if !raw_setters.get(&name).is_some_and(|current| Rc::ptr_eq(current, &setter)) {
+                    return;
+                }
+                raw_setters.remove(&name);
+                drop(raw_setters);
+                if let Some(handler) = &*context.message_handler.borrow() {
+                    unlisten_signal(&context.source(), &name, handler);
+                }
+            });
+        } else {
+            let _ = (name, decoder, set);
+        }
+    }
+    get
+}
+
+/// Creates a signal which is controlled by the server, failing if no SSE was provided.
+///
+/// This is like [`create_sse_signal`], but lets you handle a missing [`provide_sse`] call
+/// instead of logging an error, for example by rendering a fallback.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// #[component]
+/// pub fn Counter() -> impl IntoView {
+///     match leptos_sse::try_create_sse_signal::<Count>("counter") {
+///         Ok(count) => view! { <h1>"Count: " {move || count.get().value.to_string()}</h1> }.into_view(),
+///         Err(_) => view! { <p>"Live updates are unavailable"</p> }.into_view(),
+///     }
+/// }
+/// ```
+pub fn try_create_sse_signal<T>(
+    name: impl Into<Cow<'static, str>>,
+) -> Result<ReadSignal<T>, NoSseProvidedError>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de>,
+{
+    let (get, set) = create_signal(T::default());
+    register_sse_signal(namespace_name(name), get, set, None, None)?;
+    Ok(get)
+}
+
+/// Creates a signal which is controlled by the server, set at most once per `min_interval`.
+///
+/// This is like [`create_sse_signal`], but protects the UI from a server flooding the signal
+/// with updates. Each update is still applied to the json value of the signal, see
+/// [`sse_raw_signal`], but updates received within `min_interval` of the last time the signal
+/// was set are coalesced, and the signal is set to the latest value once the interval ends.
+///
+/// On native targets, which have no timers, the latest value is set by the first update
+/// received once the interval ended instead.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use leptos::*;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Price {
+/// #     pub value: f64,
+/// # }
+/// #[component]
+/// pub fn Ticker() -> impl IntoView {
+///     // Render at most 10 prices a second, however fast they change
+///     let price = leptos_sse::create_sse_signal_rate_limited::<Price>("price", Duration::from_millis(100));
+///
+///     view! {
+///         <p>{move || price.get().value.to_string()}</p>
+///     }
+/// }
+/// ```
+pub fn create_sse_signal_rate_limited<T>(
+    name: impl Into<Cow<'static, str>>,
+    min_interval: Duration,
+) -> ReadSignal<T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de>,
+{
+    let (get, set) = create_signal(T::default());
+    if let Err(err) = register_sse_signal(namespace_name(name), get, set, Some(min_interval), None)
+    {
+        err.log();
+    }
+    get
+}
+
+/// Creates a signal which is controlled by the server, calling `callback` with each value it is
+/// updated to.
+///
+/// This is like [`create_sse_signal`], but runs side effects such as showing a toast right
+/// after the signal is set by an update, in the order the updates are received. Unlike an
+/// effect reading the signal, the callback is not called with the initial value, nor when the
+/// signal is read by a component.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Inbox {
+/// #     pub unread: u32,
+/// # }
+/// # fn play_notification_sound(_unread: u32) {}
+/// #[component]
+/// pub fn Inbox() -> impl IntoView {
+///     let messages = leptos_sse::create_sse_signal_with_callback("inbox", |inbox: &Inbox| {
+///         play_notification_sound(inbox.unread);
+///     });
+///
+///     // ...
+/// #   ()
+/// }
+/// ```
+pub fn create_sse_signal_with_callback<T>(
+    name: impl Into<Cow<'static, str>>,
+    callback: impl Fn(&T) + 'static,
+) -> ReadSignal<T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de>,
+{
+    let (get, set) = create_signal(T::default());
+    if let Err(err) = register_sse_signal(
+        namespace_name(name),
+        get,
+        set,
+        None,
+        Some(Rc::new(callback)),
+    ) {
+        err.log();
+    }
+    get
+}
+
+/// The error returned by [`try_create_sse_signal`] when no SSE was provided with [`provide_sse`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NoSseProvidedError;
+
+impl NoSseProvidedError {
+    /// Logs the error, along with how to fix it, for the functions which have no error to
+    /// return.
+    fn log(self) {
+        leptos::logging::error!(
+            r#"{self}
+
+Ensure you call `leptos_sse::provide_sse("http://localhost:3000/sse")` at the highest level in your app."#
+        );
+    }
+}
+
+impl std::fmt::Display for NoSseProvidedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("no SSE was provided for the server signals.")
+    }
+}
+
+impl std::error::Error for NoSseProvidedError {}
+
+/// Called by a server signal with each value it was updated to, see
+/// [`create_sse_signal_with_callback`].
+type UpdateCallback<T> = Rc<dyn Fn(&T)>;
+
+/// Keeps `set` in sync with the server signal `name` of the provided SSE, setting it at most
+/// once per `min_interval` if given, and calling `on_update` with each value it was set to.
+fn register_sse_signal<T>(
+    name: Cow<'static, str>,
+    get: ReadSignal<T>,
+    set: WriteSignal<T>,
+    min_interval: Option<Duration>,
+    on_update: Option<UpdateCallback<T>>,
+) -> Result<(), NoSseProvidedError>
+where
+    T: Serialize + for<'de> Deserialize<'de>,
+{
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::{use_context, SignalSet, SignalWithUntracked};
+
+            let context = use_context::<ServerSignalEventSourceContext>().ok_or(NoSseProvidedError)?;
+            let value = match get.with_untracked(|value| serde_json::to_value(value)) {
+                Ok(value) => value,
+                Err(err) => {
+                    leptos::logging::error!("Server signal {} is not updated, as its value does not serialize to json: {}", name, err);
+                    return Ok(());
+                }
+            };
+
+            // Set right after the json value is patched, rather than by an effect tracking it
+            let setter: Setter = match min_interval {
+                Some(min_interval) => rate_limited_setter(set, min_interval),
+                None => Rc::new(move |doc| {
+                    let value = T::deserialize(doc).map_err(|err| format!("{}: {}", std::any::type_name::<T>(), err))?;
+                    set.set(value);
+                    if let Some(on_update) = &on_update {
+                        get.with_untracked(|value| on_update(value));
+                    }
+                    Ok(())
+                }),
+            };
+            register_json_signal(&context, name, value, setter);
+        } else {
+            let _ = (name, get, set, min_interval, on_update);
+        }
+    }
+
+    Ok(())
+}
+
+/// Patches a json value with the updates of the server signal `name`, starting from `value`,
+/// and calls `setter` with each patched value.
+///
+/// A json value already registered under `name` is shared rather than replaced, so that every
+/// signal and field created for `name` is updated. `setter` is then called with its current
+/// value if the updates received so far changed it.
+#[cfg(any(target_arch = "wasm32", feature = "testing"))]
+fn register_json_signal(
+    context: &ServerSignalEventSourceContext,
+    name: Cow<'static, str>,
+    value: Value,
+    setter: Setter,
+) -> RwSignal<Value> {
+    use leptos::{create_rw_signal, on_cleanup, SignalGetUntracked};
+
+    let ServerSignalEventSourceContext {
+        state_signals,
+        delayed_updates,
+        message_handler,
+        setters,
+        initial_values,
+        versions,
+        ..
+    } = context.clone();
+    let existing = state_signals.borrow().get(&name).copied();
+    let signal = match existing {
+        Some(signal) => {
+            setters
+                .borrow_mut()
+                .entry(name.clone())
+                .or_default()
+                .push(setter.clone());
+            let current = signal.get_untracked();
+            if current != value {
+                if let Err(err) = setter(&current) {
+                    leptos::logging::error!(
+                        "Server signal {} does not match its current value: {}",
+                        name,
+                        err
+                    );
+                }
+            }
+            signal
+        }
+        None => {
+            initial_values
+                .borrow_mut()
+                .insert(name.clone(), value.clone());
+            let signal = create_rw_signal(value);
+
+            // Updates are sent as events named after the signal
+            if let Some(handler) = &*message_handler.borrow() {
+                listen_signal(&context.source(), &name, handler);
+            }
+            state_signals.borrow_mut().insert(name.clone(), signal);
+            setters
+                .borrow_mut()
+                .insert(name.clone(), vec![setter.clone()]);
+
+            // Updates may have been received before the signal was created, for example when
+            // it is created on a route change while the connection is already open
+            let queued = if context.paused.get() {
+                None
+            } else {
+                delayed_updates.borrow_mut().remove(&name)
+            };
+            if let Some(queued) = queued {
+                update_signal(context, name.clone(), signal, &queued);
+            }
+            signal
+        }
+    };
+
+    // Stop updating the signal once the owners of every registration sharing it are disposed.
+    // The signals may have been cleared and `name` registered again since, in which case the
+    // newer signal is left untouched.
+    let context = context.clone();
+    on_cleanup(move || {
+        let mut setters = setters.borrow_mut();
+        let Some(registered) = setters.get_mut(&name) else {
+            return;
+        };
+        registered.retain(|other| !Rc::ptr_eq(other, &setter));
+        if !registered.is_empty() {
+            return;
+        }
+        setters.remove(&name);
+        drop(setters);
+        let mut state_signals = state_signals.borrow_mut();
+        if state_signals.get(&name) != Some(&signal) {
+            return;
+        }
+        state_signals.remove(&name);
+        delayed_updates.borrow_mut().remove(&name);
+        initial_values.borrow_mut().remove(&name);
+        versions.borrow_mut().remove(&name);
+        if let Some(handler) = &*message_handler.borrow() {
+            unlisten_signal(&context.source(), &name, handler);
+        }
+    });
+    signal
+}
+
+/// Closes the SSE connection provided by [`provide_sse`].
+///
+/// Server signals keep their last received value, but no longer receive updates. Any patches
+/// that were queued for signals which have not been created yet are discarded, so they are not
+/// applied if a new connection is provided later on.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// #[component]
+/// pub fn Settings() -> impl IntoView {
+///     // This page doesn't need live data
+///     leptos_sse::close_sse();
+///
+///     // ...
+/// #   ()
+/// }
+/// ```
+pub fn close_sse() {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::{use_context, SignalSet};
+
+            if let Some(es) = use_context::<ServerSignalEventSourceContext>() {
+                detach(&es);
+                cancel_reconnect(&es);
+                es.source().close();
+                es.state_signals.borrow_mut().clear();
+                es.delayed_updates.borrow_mut().clear();
+                es.queued_updates.borrow_mut().clear();
+                #[cfg(feature = "raw")]
+                es.paused_raw.borrow_mut().clear();
+                es.setters.borrow_mut().clear();
+                es.raw_setters.borrow_mut().clear();
+                es.initial_values.borrow_mut().clear();
+                es.versions.borrow_mut().clear();
+                // Closing the event source does not fire an error event
+                es.connection_state.set(ConnectionState::Closed);
+            }
+        }
+    }
+}
+
+/// Stops applying the updates received through the SSE provided by [`provide_sse`], until
+/// [`sse_resume`] is called.
+///
+/// The updates are queued rather than dropped, so the server signals keep their value in the
+/// meantime, for example during an animation, and catch up at once when resumed.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn play_transition(_done: impl FnOnce() + 'static) {}
+/// leptos_sse::sse_pause();
+/// play_transition(move || leptos_sse::sse_resume());
+/// ```
+pub fn sse_pause() {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            if let Some(es) = use_context::<ServerSignalEventSourceContext>() {
+                es.paused.set(true);
+            }
+        }
+    }
+}
+
+/// Applies the updates queued since [`sse_pause`] was called, and applies further updates as
+/// they are received again.
+///
+/// The queued updates are applied together, in a single [`batch`](leptos::batch), so
+/// anything depending on several server signals runs once.
+pub fn sse_resume() {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            if let Some(es) = use_context::<ServerSignalEventSourceContext>() {
+                if es.paused.replace(false) {
+                    flush_paused(&es);
+                }
+            }
+        }
+    }
+}
+
+/// Replaces the SSE connection provided by [`provide_sse`] with a new one to `url`.
+///
+/// This is meant for urls which expire, for example because they carry a short-lived token:
+/// the browser keeps reconnecting to the url it was given, even once it is no longer valid.
+/// The new connection is opened with the same options and codec as the previous one. Server
+/// signals keep their value and are updated through the new connection, without being created
+/// again, and updates queued for signals which have not been created yet are kept.
+///
+/// Code which looks up the `ServerSignalEventSource` context afterwards gets the new event
+/// source, copies taken before keep referring to the closed one.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::SignalGetUntracked;
+/// # use leptos_sse::ConnectionState;
+/// # fn refresh_token() -> String {
+/// #     String::new()
+/// # }
+/// leptos_sse::on_sse_error(move |_| {
+///     if leptos_sse::sse_connection_state().get_untracked() == ConnectionState::Closed {
+///         let url = format!("/sse?token={}", refresh_token());
+///         leptos_sse::reconnect_sse(&url).unwrap();
+///     }
+/// });
+/// ```
+pub fn reconnect_sse(url: &str) -> Result<(), JsValue> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            let Some(es) = use_context::<ServerSignalEventSourceContext>() else {
+                return Err(JsValue::from_str("no SSE was provided to reconnect"));
+            };
+            reopen(&es, url)?;
+        } else {
+            let _ = url;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets the url server signals fetch their value from when they miss updates, or an update
+/// fails to apply, rather than reconnecting the SSE provided by [`provide_sse`].
+///
+/// The name of the signal is posted to `url`, which must respond with the current value of the
+/// signal, as encoded by [`resync_response`]. The value replaces the one of the signal, and
+/// the next update of the SSE applies to it, so the server should diff its updates against the
+/// value it responded with. The updates of the signal received while its value is fetched are
+/// dropped, as the value responded with is expected to hold them. Call it right after
+/// [`provide_sse`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     leptos_sse::provide_sse("/sse").unwrap();
+///     leptos_sse::provide_sse_resync_url("/sse/resync");
+///
+///     // ...
+/// #   ()
+/// }
+/// ```
+pub fn provide_sse_resync_url(url: &str) {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            match use_context::<ServerSignalEventSourceContext>() {
+                Some(es) => *es.resync_source.borrow_mut() = Some(ResyncSource::Url(url.to_owned())),
+                None => NoSseProvidedError.log(),
+            }
+        } else {
+            let _ = url;
+        }
+    }
+}
+
+/// Makes server signals fetch their value through the [`sse_snapshot`] server function when
+/// they miss updates, or an update fails to apply, rather than reconnecting the SSE provided by
+/// [`provide_sse`].
+///
+/// This is like [`provide_sse_resync_url`], but uses the server functions of leptos rather than
+/// an endpoint wired by hand. Register the current value of each signal on the server with
+/// [`register_sse_snapshot`]. Call it right after [`provide_sse`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     leptos_sse::provide_sse("/sse").unwrap();
+///     leptos_sse::provide_sse_resync_server_fn();
+///
+///     // ...
+/// #   ()
+/// }
+/// ```
+pub fn provide_sse_resync_server_fn() {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            match use_context::<ServerSignalEventSourceContext>() {
+                Some(es) => *es.resync_source.borrow_mut() = Some(ResyncSource::ServerFn),
+                None => NoSseProvidedError.log(),
+            }
+        }
+    }
+}
+
+/// Fetches the current value of the server signal `name` from the url set by
+/// [`provide_sse_resync_url`], or through [`sse_snapshot`] once
+/// [`provide_sse_resync_server_fn`] was called, for example once the app suspects it is out of
+/// date.
+///
+/// Without either, this reconnects the SSE provided by [`provide_sse`] instead, which resyncs
+/// every signal. Outside the browser, nothing is fetched.
+pub fn request_resync(name: impl Into<Cow<'static, str>>) {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            let name = namespace_name(name);
+            match use_context::<ServerSignalEventSourceContext>() {
+                Some(es) => resync_signal(&es, &name),
+                None => NoSseProvidedError.log(),
+            }
+        } else {
+            let _ = name;
+        }
+    }
+}
+
+/// Encodes `value` as the response to a resync request of the signal `name`, posted by the
+/// client to the url set with [`provide_sse_resync_url`].
+///
+/// The body of the request is the name of the signal. Respond with the returned json.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(all(feature = "ssr", feature = "axum"))]
+/// # mod app {
+/// # use axum::extract::State;
+/// # use axum::http::StatusCode;
+/// # use leptos_sse::SignalState;
+/// # use serde::Serialize;
+/// # #[derive(Clone, Serialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// # #[derive(Clone)]
+/// # pub struct AppState {
+/// #     pub count: SignalState<Count>,
+/// # }
+/// async fn handle_resync(name: String, State(state): State<AppState>) -> Result<String, StatusCode> {
+///     match name.as_str() {
+///         "counter" => leptos_sse::resync_response(name, &state.count.get())
+///             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR),
+///         _ => Err(StatusCode::NOT_FOUND),
+///     }
+/// }
+/// # }
+/// ```
+///
+/// This function can fail if serilization of `T` fails.
+pub fn resync_response<T>(
+    name: impl Into<Cow<'static, str>>,
+    value: &T,
+) -> Result<String, serde_json::Error>
+where
+    T: Serialize,
+{
+    serde_json::to_string(&ServerSignalUpdate::snapshot(name, value)?)
+}
+
+/// Returns a signal tracking the state of the SSE connection provided by [`provide_sse`].
+///
+/// The signal starts as [`ConnectionState::Connecting`], becomes [`ConnectionState::Open`] once
+/// the connection is established, and falls back to [`ConnectionState::Connecting`] while the
+/// browser is reconnecting. It becomes [`ConnectionState::Closed`] when the browser gives up.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # use leptos_sse::ConnectionState;
+/// #[component]
+/// pub fn Status() -> impl IntoView {
+///     let state = leptos_sse::sse_connection_state();
+///
+///     view! {
+///         <Show when=move || state.get() == ConnectionState::Connecting>
+///             <p>"Reconnecting…"</p>
+///         </Show>
+///     }
+/// }
+/// ```
+pub fn sse_connection_state() -> ReadSignal<ConnectionState> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            if let Some(ServerSignalEventSourceContext { connection_state, .. }) = use_context::<ServerSignalEventSourceContext>() {
+                connection_state.read_only()
+            } else {
+                NoSseProvidedError.log();
+                create_signal(ConnectionState::Closed).0
+            }
+        } else {
+            create_signal(ConnectionState::default()).0
+        }
+    }
+}
+
+/// Returns a signal counting the times the SSE provided by [`provide_sse`] reconnected.
+///
+/// It is incremented each time the connection opens again after the first time, whether the
+/// browser reconnected on its own, a [`ReconnectPolicy`] did, or [`reconnect_sse`] was called.
+/// Along with [`sse_connection_state`], this tells how flaky the connection of a user is.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// #[component]
+/// pub fn Health() -> impl IntoView {
+///     let reconnects = leptos_sse::sse_reconnect_count();
+///
+///     view! {
+///         <p>"Reconnections: " {reconnects}</p>
+///     }
+/// }
+/// ```
+pub fn sse_reconnect_count() -> ReadSignal<u32> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            if let Some(ServerSignalEventSourceContext { reconnect_count, .. }) = use_context::<ServerSignalEventSourceContext>() {
+                reconnect_count.read_only()
+            } else {
+                NoSseProvidedError.log();
+                create_signal(0).0
+            }
+        } else {
+            create_signal(0).0
+        }
+    }
+}
+
+/// Returns a signal holding the last error reported by the server through the SSE.
+///
+/// This lets you distinguish a signal that stopped updating because the server failed from
+/// one that is just idle.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// #[component]
+/// pub fn Errors() -> impl IntoView {
+///     let error = leptos_sse::sse_last_error();
+///
+///     view! {
+///         <p>{move || error.get().map(|err| err.to_string())}</p>
+///     }
+/// }
+/// ```
+pub fn sse_last_error() -> ReadSignal<Option<ServerSignalError>> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            if let Some(ServerSignalEventSourceContext { last_error, .. }) = use_context::<ServerSignalEventSourceContext>() {
+                last_error.read_only()
+            } else {
+                NoSseProvidedError.log();
+                create_signal(None).0
+            }
+        } else {
+            create_signal(None).0
+        }
+    }
+}
+
+/// Calls `handler` on each connection error of the SSE provided by [`provide_sse`].
+///
+/// This is called after [`sse_connection_state`] is updated, so the handler can tell whether
+/// the browser is reconnecting or gave up, for example to reconnect with a new token. Handlers
+/// are called in the order they were registered, within the reactive owner which provided the
+/// SSE, so they can use the other functions of this crate. Errors sent by the server for a
+/// signal are reported by [`sse_last_error`] instead.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     leptos_sse::provide_sse("http://localhost:3000/sse").unwrap();
+///     leptos_sse::on_sse_error(|event| {
+///         leptos::logging::warn!("SSE connection error: {:?}", event);
+///     });
+///
+///     // ...
+/// #   ()
+/// }
+/// ```
+pub fn on_sse_error(handler: impl Fn(web_sys::Event) + 'static) {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            if let Some(es) = use_context::<ServerSignalEventSourceContext>() {
+                es.error_handlers.borrow_mut().push(Rc::new(handler));
+            } else {
+                NoSseProvidedError.log();
+            }
+        } else {
+            let _ = handler;
+        }
+    }
+}
+
+/// Calls `handler` each time the SSE provided by [`provide_sse`] connects, including when it
+/// reconnects.
+///
+/// This is the place for side effects which must follow the connection, such as fetching a
+/// snapshot which the updates of the stream then patch. Register it right after providing the
+/// SSE, as it is not called for a connection which is already open. Handlers are called in the
+/// order they were registered, after [`sse_connection_state`] is updated, within the reactive
+/// owner which provided the SSE.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     leptos_sse::provide_sse("/sse").unwrap();
+///     leptos_sse::on_sse_open(move || {
+///         spawn_local(async move {
+///             // Fetch the history the stream does not send
+///         });
+///     });
+///
+///     // ...
+/// #   ()
+/// }
+/// ```
+pub fn on_sse_open(handler: impl Fn() + 'static) {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            if let Some(es) = use_context::<ServerSignalEventSourceContext>() {
+                es.open_handlers.borrow_mut().push(Rc::new(handler));
+            } else {
+                NoSseProvidedError.log();
+            }
+        } else {
+            let _ = handler;
+        }
+    }
+}
+
+/// Lends the `EventSource` of the SSE provided by [`provide_sse`] to `f`, returning what it
+/// returns.
+///
+/// This is an escape hatch for what the crate does not offer, such as reading the url of the
+/// connection or adding a one-off listener. The handle is only lent for the call: the
+/// connection may be replaced later on, by [`reconnect_sse`] or by a [`ReconnectPolicy`], so
+/// keeping a clone of it around is likely to act on a stale connection. Listeners added to it
+/// are dropped along with it. Closing it is better left to [`close_sse`], which also stops the
+/// server signals.
+///
+/// Returns `None` when no SSE was provided, when it reads the stream through `fetch` (see
+/// [`Transport::Fetch`]), and outside the browser.
+///
+/// # Example
+///
+/// ```no_run
+/// let url = leptos_sse::with_sse_event_source(|source| source.url());
+/// ```
+pub fn with_sse_event_source<R>(f: impl FnOnce(&web_sys::EventSource) -> R) -> Option<R> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            match use_context::<ServerSignalEventSourceContext>()?.source() {
+                Source::EventSource(source) => Some(f(&source)),
+                _ => None,
+            }
+        } else {
+            let _ = f;
+            None
+        }
+    }
+}
+
+/// Returns a signal holding the time the server signal `name` was last updated at.
+///
+/// The time is the [`performance.now()`] of the browser when the update was applied, in
+/// milliseconds. It is `None` until the signal receives its first update.
+///
+/// [`performance.now()`]: https://developer.mozilla.org/docs/Web/API/Performance/now
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # use leptos_sse::create_sse_signal;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Prices {
+/// #     pub values: Vec<f64>,
+/// # }
+/// # fn use_timestamp() -> Signal<f64> {
+/// #     Signal::derive(|| 0.0)
+/// # }
+/// #[component]
+/// pub fn Prices() -> impl IntoView {
+///     let prices = create_sse_signal::<Prices>("prices");
+///     let last_update = leptos_sse::sse_signal_last_update("prices");
+///     let now = use_timestamp();
+///
+///     // Gray out prices which have not been refreshed for 10 seconds
+///     let stale = move || last_update.get().map_or(true, |at| now.get() - at > 10_000.0);
+///
+///     // ...
+/// #   ()
+/// }
+/// ```
+pub fn sse_signal_last_update(name: impl Into<Cow<'static, str>>) -> ReadSignal<Option<f64>> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            if let Some(es) = use_context::<ServerSignalEventSourceContext>() {
+                es.last_update(namespace_name(name)).read_only()
+            } else {
+                NoSseProvidedError.log();
+                create_signal(None).0
+            }
+        } else {
+            let _ = name;
+            create_signal(None).0
+        }
+    }
+}
+
+/// Returns a signal which is `true` once the server signal `name` received its first update.
+///
+/// Until then, the signal holds the value it was created with, which the server may well send
+/// too. This tells both apart, for example to show a loading state. Updates received before the
+/// signal was created count once they are applied.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # use leptos_sse::create_sse_signal;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// #[component]
+/// pub fn Counter() -> impl IntoView {
+///     let count = create_sse_signal::<Count>("counter");
+///     let initialized = leptos_sse::sse_signal_initialized("counter");
+///
+///     view! {
+///         <Show when=move || initialized.get() fallback=|| view! { <p>"Loading..."</p> }>
+///             <p>"Count: " {move || count.get().value.to_string()}</p>
+///         </Show>
+///     }
+/// }
+/// ```
+pub fn sse_signal_initialized(name: impl Into<Cow<'static, str>>) -> ReadSignal<bool> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            if let Some(es) = use_context::<ServerSignalEventSourceContext>() {
+                es.initialized(namespace_name(name)).read_only()
+            } else {
+                NoSseProvidedError.log();
+                create_signal(false).0
+            }
+        } else {
+            let _ = name;
+            create_signal(false).0
+        }
+    }
+}
+
+/// Returns the patched json value of the server signal `name`, before it is deserialized.
+///
+/// This is `None` until the signal is created with [`create_sse_signal`] or one of its
+/// variants, and after it stopped receiving updates. The value is useful to render fields the
+/// type of the signal does not know about, or to inspect the signals while debugging.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// # use leptos_sse::create_sse_signal;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// #[component]
+/// pub fn Inspector() -> impl IntoView {
+///     let count = create_sse_signal::<Count>("counter");
+///     let json = leptos_sse::sse_raw_signal("counter").expect("counter was created");
+///
+///     view! {
+///         <pre>{move || json.get().to_string()}</pre>
+///     }
+/// }
+/// ```
+pub fn sse_raw_signal(name: impl Into<Cow<'static, str>>) -> Option<ReadSignal<Value>> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            let es = use_context::<ServerSignalEventSourceContext>()?;
+            let signal = es.state_signals.borrow().get(&namespace_name(name)).copied();
+            signal.map(|signal| signal.read_only())
+        } else {
+            let _ = name;
+            None
+        }
+    }
+}
+
+/// Returns a signal holding the updates the client failed to apply, oldest first.
+///
+/// An update fails when its json patch does not apply to the value of the signal, or when the
+/// patched value no longer deserializes into the type of the signal. Either is otherwise only
+/// logged to the console. Only the last 100 errors are kept.
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos::*;
+/// #[component]
+/// pub fn Diagnostics() -> impl IntoView {
+///     let errors = leptos_sse::sse_error_log();
+///
+///     view! {
+///         <ul>
+///             {move || errors.get().iter().map(|err| view! { <li>{err.to_string()}</li> }).collect_view()}
+///         </ul>
+///     }
+/// }
+/// ```
+pub fn sse_error_log() -> ReadSignal<Vec<AppliedPatchError>> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            if let Some(ServerSignalEventSourceContext { error_log, .. }) = use_context::<ServerSignalEventSourceContext>() {
+                error_log.read_only()
+            } else {
+                NoSseProvidedError.log();
+                create_signal(Vec::new()).0
+            }
+        } else {
+            create_signal(Vec::new()).0
+        }
+    }
+}
+
+/// Returns the names of the server signals currently registered with the SSE provided by
+/// [`provide_sse`], sorted by name.
+///
+/// A signal is registered from its creation until its reactive owner is disposed. A signal
+/// which is stuck at its initial value, but missing here, was never created on the client, or
+/// was created under another name. This is empty if no SSE was provided, and on the server.
+///
+/// # Example
+///
+/// ```no_run
+/// leptos::logging::log!("Server signals: {:?}", leptos_sse::sse_registered_signals());
+/// ```
+pub fn sse_registered_signals() -> Vec<Cow<'static, str>> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            let Some(es) = use_context::<ServerSignalEventSourceContext>() else {
+                return Vec::new();
+            };
+            let mut names = es.signal_names();
+            names.sort();
+            names
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Returns the names of the server signals with updates queued until they are created, sorted
+/// by name.
+///
+/// Updates are queued when the server sends them before the signal is created on the client.
+/// A name which stays here was never created on the client, or was created under another name
+/// than the one the server uses. This is empty if no SSE was provided, and on the server.
+///
+/// # Example
+///
+/// ```no_run
+/// leptos::logging::log!("Waiting for: {:?}", leptos_sse::sse_pending_updates());
+/// ```
+pub fn sse_pending_updates() -> Vec<Cow<'static, str>> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+            use leptos::use_context;
+
+            let Some(es) = use_context::<ServerSignalEventSourceContext>() else {
+                return Vec::new();
+            };
+            let mut names: Vec<_> = es.delayed_updates.borrow().keys().cloned().collect();
+            names.sort();
+            names
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(any(target_arch = "wasm32", feature = "testing"))] {
+        use std::cell::{Cell, RefCell};
+        use std::collections::HashMap;
+        use std::ops::{Deref, DerefMut};
+
+        use js_sys::Function;
+        use wasm_bindgen::prelude::Closure;
+        use web_sys::{EventSource, EventTarget};
+        use leptos::{provide_context, Owner, RwSignal};
+        use crate::codec::ServerSignalMessage;
+        use crate::fetch::FetchEventSource;
+
+        /// Provides the context for the server signal `web_sys::EventSource`.
+        ///
+        /// You can use this via `use_context::<ServerSignalEventSource>()` to
+        /// access the `EventSource` to set up additional event listeners and etc.
+        ///
+        /// It is only provided for connections using [`Transport::EventSource`].
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        pub struct ServerSignalEventSource(pub EventSource);
+
+        impl Deref for ServerSignalEventSource {
+            type Target = EventSource;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl DerefMut for ServerSignalEventSource {
+            fn deref_mut(&mut self) -> &mut Self::Target {
                 &mut self.0
             }
         }
 
-        #[derive(Clone, Debug, PartialEq, Eq)]
+        /// A map shared with the event source callbacks, keyed by signal name.
+        type SignalMap<T> = Rc<RefCell<HashMap<Cow<'static, str>, T>>>;
+
+        /// Sets the signal read by components to a json value, failing if it does not
+        /// deserialize into the type of the signal.
+        type Setter = Rc<dyn Fn(&Value) -> Result<(), String>>;
+
+        /// Sets the signal read by components to the value decoded from raw bytes, failing if
+        /// they do not decode.
+        type RawSetter = Rc<dyn Fn(&[u8]) -> Result<(), String>>;
+
+        /// A handler registered with `on_sse_error`.
+        type ErrorHandler = Rc<dyn Fn(web_sys::Event)>;
+
+        /// A handler registered with [`on_sse_open`].
+        type OpenHandler = Rc<dyn Fn()>;
+
+        /// The closure behind a listener of the event source.
+        type Listener = Closure<dyn FnMut(web_sys::Event)>;
+
+        /// Resolves `url` against the url of the current page, as a browser resolves a link.
+        ///
+        /// Absolute urls are returned as they are.
+        fn resolve_url(url: &str) -> Result<String, JsValue> {
+            let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window to resolve the SSE url against"))?;
+            let base = window.location().href()?;
+            Ok(web_sys::Url::new_with_base(url, &base)?.href())
+        }
+
+        /// Where a signal which missed updates fetches its value from.
+        #[derive(Clone, Debug)]
+        enum ResyncSource {
+            /// The url set by `provide_sse_resync_url`.
+            Url(String),
+            /// The `sse_snapshot` server function, set by `provide_sse_resync_server_fn`.
+            ServerFn,
+        }
+
+        /// The event source of either transport.
+        #[derive(Clone)]
+        enum Source {
+            EventSource(EventSource),
+            Fetch(FetchEventSource),
+            /// The connection of a `MockSse`, which has no event source.
+            #[cfg(feature = "testing")]
+            Mock(crate::testing::MockSource),
+        }
+
+        impl Source {
+            fn open(url: &str, settings: &SseOptions) -> Result<Self, JsValue> {
+                let url = &resolve_url(url)?;
+                match settings.transport {
+                    Transport::EventSource => {
+                        let source = match &settings.options {
+                            Some(options) => EventSource::new_with_event_source_init_dict(url, options)?,
+                            None => EventSource::new(url)?,
+                        };
+                        Ok(Source::EventSource(source))
+                    }
+                    Transport::Fetch => {
+                        FetchEventSource::new(url, settings.options.as_ref(), &settings.headers).map(Source::Fetch)
+                    },
+                }
+            }
+
+            fn ready_state(&self) -> u16 {
+                match self {
+                    Source::EventSource(source) => source.ready_state(),
+                    Source::Fetch(source) => source.ready_state(),
+                    #[cfg(feature = "testing")]
+                    Source::Mock(source) => source.ready_state(),
+                }
+            }
+
+            fn url(&self) -> String {
+                match self {
+                    Source::EventSource(source) => source.url(),
+                    Source::Fetch(source) => source.url(),
+                    #[cfg(feature = "testing")]
+                    Source::Mock(_) => String::new(),
+                }
+            }
+
+            fn close(&self) {
+                match self {
+                    Source::EventSource(source) => source.close(),
+                    Source::Fetch(source) => source.close(),
+                    #[cfg(feature = "testing")]
+                    Source::Mock(source) => source.close(),
+                }
+            }
+
+            /// Provides the `ServerSignalEventSource` context, for the native transport.
+            fn provide(&self, owner: Option<Owner>) {
+                use leptos::with_owner;
+
+                if let Source::EventSource(source) = self {
+                    let provide = || provide_context(ServerSignalEventSource(source.clone()));
+                    match owner {
+                        Some(owner) => with_owner(owner, provide),
+                        None => provide(),
+                    }
+                }
+            }
+        }
+
+        impl Source {
+            /// The event target of the transport, which a mock connection does not have.
+            fn target(&self) -> Option<&EventTarget> {
+                match self {
+                    Source::EventSource(source) => Some(source),
+                    Source::Fetch(source) => Some(source),
+                    #[cfg(feature = "testing")]
+                    Source::Mock(_) => None,
+                }
+            }
+
+            fn add_event_listener_with_callback(&self, event_type: &str, listener: &Function) -> Result<(), JsValue> {
+                match self.target() {
+                    Some(target) => target.add_event_listener_with_callback(event_type, listener),
+                    None => Ok(()),
+                }
+            }
+
+            fn remove_event_listener_with_callback(&self, event_type: &str, listener: &Function) -> Result<(), JsValue> {
+                match self.target() {
+                    Some(target) => target.remove_event_listener_with_callback(event_type, listener),
+                    None => Ok(()),
+                }
+            }
+        }
+
+        #[derive(Clone)]
         struct ServerSignalEventSourceContext {
-            inner: EventSource,
+            // Replaced by `reconnect_sse`
+            inner: Rc<RefCell<Source>>,
+            settings: Rc<RefCell<SseOptions>>,
             // References to these are kept by the closure for the callback
             // onmessage callback on the event source
-            state_signals: Rc<RefCell<HashMap<Cow<'static, str>, RwSignal<Value>>>>,
+            state_signals: SignalMap<RwSignal<Value>>,
             // When the event source is first established, leptos may not have
             // completed the traversal that sets up all of the state signals.
             // Without that, we don't have a base state to apply the patches to,
             // and therefore we must keep a record of the patches to apply after
             // the state has been set up.
-            delayed_updates: Rc<RefCell<HashMap<Cow<'static, str>, Vec<Patch>>>>,
+            delayed_updates: SignalMap<Vec<ServerSignalUpdate>>,
+            // The updates received since the batching window of the connection started
+            queued_updates: Rc<RefCell<Vec<ServerSignalUpdate>>>,
+            // The listener for `message` events, which is also registered as the listener
+            // for the events named after each server signal
+            message_handler: Rc<RefCell<Option<Function>>>,
+            // The listeners registered by `attach` for other events, by event type
+            listeners: Rc<RefCell<Vec<(&'static str, Function)>>>,
+            // The closures behind the listeners registered by `attach`, dropped by `detach`
+            // once the listeners are removed, so providing the SSE again does not leak them
+            closures: Rc<RefCell<Vec<Listener>>>,
+            // Kept up to date by the open and error listeners on the event source
+            connection_state: RwSignal<ConnectionState>,
+            // Set once the connection opened, after which each open is a reconnection
+            has_opened: Rc<Cell<bool>>,
+            // Incremented by the open listener on each reconnection
+            reconnect_count: RwSignal<u32>,
+            // Set by the error listener when the server sends an error event
+            last_error: RwSignal<Option<ServerSignalError>>,
+            // The updates which failed to apply, up to `ERROR_LOG_CAPACITY`
+            error_log: RwSignal<Vec<AppliedPatchError>>,
+            // The time each signal was last updated at, created on demand
+            // by `sse_signal_last_update` with the owner of the context
+            last_updates: SignalMap<RwSignal<Option<f64>>>,
+            // Whether each signal received an update, created on demand
+            // by `sse_signal_initialized` with the owner of the context
+            initialized: SignalMap<RwSignal<bool>>,
+            owner: Option<Owner>,
+            // Called by the error listener on connection errors
+            error_handlers: Rc<RefCell<Vec<ErrorHandler>>>,
+            // Called by the open listener once connected
+            open_handlers: Rc<RefCell<Vec<OpenHandler>>>,
+            // Keep the signals read by components in sync with `state_signals`
+            setters: SignalMap<Vec<Setter>>,
+            // The signals created by `create_sse_signal_raw`, which are set rather than patched
+            raw_setters: SignalMap<RawSetter>,
+            // The value each signal was created with, which a new stream of the server
+            // diffs against again
+            initial_values: SignalMap<Value>,
+            // The version of the last update applied to each signal, to detect missed updates
+            versions: SignalMap<u64>,
+            // Set while a reconnection to resync the signals is scheduled
+            resync_pending: Rc<Cell<bool>>,
+            // Set by `provide_sse_resync_url` and `provide_sse_resync_server_fn`, to fetch the
+            // value of a signal which missed updates rather than reconnecting
+            resync_source: Rc<RefCell<Option<ResyncSource>>>,
+            // The signals whose value is being fetched from `resync_source`, with the version of
+            // the last update received meanwhile, which the value fetched holds
+            resync_requests: SignalMap<u64>,
+            // Set by `sse_pause`, queuing the updates in `delayed_updates` until `sse_resume`
+            paused: Rc<Cell<bool>>,
+            // The last value received for each raw signal while paused
+            #[cfg(feature = "raw")]
+            paused_raw: SignalMap<String>,
+            // The failed attempts in a row of the reconnect policy, reset once open
+            reconnect_attempts: Rc<Cell<u32>>,
+            // Set while a reconnection of the reconnect policy is scheduled
+            reconnect_timer: Rc<Cell<Option<leptos::leptos_dom::helpers::TimeoutHandle>>>,
         }
 
-        #[inline]
-        fn provide_sse_inner(url: &str) -> Result<(), JsValue> {
-            use web_sys::MessageEvent;
-            use wasm_bindgen::{prelude::Closure, JsCast};
-            use leptos::{use_context, SignalUpdate};
-            use js_sys::{Function, JsString};
-
-            if use_context::<ServerSignalEventSourceContext>().is_none() {
-                let es = EventSource::new(url)?;
-                provide_context(ServerSignalEventSource(es.clone()));
-                provide_context(ServerSignalEventSourceContext { inner: es, state_signals: Default::default(), delayed_updates: Default::default() });
-            }
-
-            let es = use_context::<ServerSignalEventSourceContext>().unwrap();
-            let handlers = es.state_signals.clone();
-            let delayed_updates = es.delayed_updates.clone();
-            let callback = Closure::wrap(Box::new(move |event: MessageEvent| {
-                let ws_string = event.data().dyn_into::<JsString>().unwrap().as_string().unwrap();
-                if let Ok(update_signal) = serde_json::from_str::<ServerSignalUpdate>(&ws_string) {
-                    let handler_map = (*handlers).borrow();
-                    let name = &update_signal.name;
-                    let mut delayed_map = (*delayed_updates).borrow_mut();
-                    if let Some(signal) = handler_map.get(name) {
-                        if let Some(delayed_patches) = delayed_map.remove(name) {
-                            signal.update(|doc| {
-                                for patch in delayed_patches {
-                                    json_patch::patch(doc, &patch).unwrap();
-                                }
-                            });
+        impl ServerSignalEventSourceContext {
+            fn new(inner: Source, settings: SseOptions) -> Self {
+                use leptos::create_rw_signal;
+
+                ServerSignalEventSourceContext {
+                    connection_state: create_rw_signal(ConnectionState::from_ready_state(inner.ready_state())),
+                    has_opened: Rc::new(Cell::new(inner.ready_state() == EventSource::OPEN)),
+                    reconnect_count: create_rw_signal(0),
+                    last_error: create_rw_signal(None),
+                    error_log: create_rw_signal(Vec::new()),
+                    inner: Rc::new(RefCell::new(inner)),
+                    settings: Rc::new(RefCell::new(settings)),
+                    state_signals: Default::default(),
+                    delayed_updates: Default::default(),
+                    queued_updates: Default::default(),
+                    message_handler: Default::default(),
+                    listeners: Default::default(),
+                    closures: Default::default(),
+                    last_updates: Default::default(),
+                    initialized: Default::default(),
+                    owner: Owner::current(),
+                    error_handlers: Default::default(),
+                    open_handlers: Default::default(),
+                    setters: Default::default(),
+                    raw_setters: Default::default(),
+                    initial_values: Default::default(),
+                    versions: Default::default(),
+                    resync_pending: Default::default(),
+                    resync_source: Default::default(),
+                    resync_requests: Default::default(),
+                    paused: Default::default(),
+                    #[cfg(feature = "raw")]
+                    paused_raw: Default::default(),
+                    reconnect_attempts: Default::default(),
+                    reconnect_timer: Default::default(),
+                }
+            }
+
+            /// The current event source.
+            fn source(&self) -> Source {
+                self.inner.borrow().clone()
+            }
+
+            fn last_update(&self, name: Cow<'static, str>) -> RwSignal<Option<f64>> {
+                use leptos::{create_rw_signal, with_owner};
+
+                let create = || create_rw_signal(None);
+                *self.last_updates.borrow_mut().entry(name).or_insert_with(|| match self.owner {
+                    Some(owner) => with_owner(owner, create),
+                    None => create(),
+                })
+            }
+
+            /// The names of the registered signals, which updates are sent as events named after.
+            fn signal_names(&self) -> Vec<Cow<'static, str>> {
+                let mut names: Vec<_> = self.state_signals.borrow().keys().cloned().collect();
+                names.extend(self.raw_setters.borrow().keys().cloned());
+                names
+            }
+
+            /// Records that the signal `name` was updated.
+            fn mark_updated(&self, name: Cow<'static, str>) {
+                use leptos::{SignalGetUntracked, SignalSet};
+
+                self.last_update(name.clone()).set(now());
+                let initialized = self.initialized(name);
+                if !initialized.get_untracked() {
+                    initialized.set(true);
+                }
+            }
+
+            fn initialized(&self, name: Cow<'static, str>) -> RwSignal<bool> {
+                use leptos::{create_rw_signal, with_owner};
+
+                let create = || create_rw_signal(false);
+                *self.initialized.borrow_mut().entry(name).or_insert_with(|| match self.owner {
+                    Some(owner) => with_owner(owner, create),
+                    None => create(),
+                })
+            }
+        }
+
+        impl ConnectionState {
+            fn from_ready_state(ready_state: u16) -> Self {
+                match ready_state {
+                    EventSource::OPEN => ConnectionState::Open,
+                    EventSource::CLOSED => ConnectionState::Closed,
+                    _ => ConnectionState::Connecting,
+                }
+            }
+        }
+
+        /// Sets `set` to the value of each update, at most once per `min_interval`.
+        ///
+        /// The values received in between are still deserialized, so an invalid one is rolled
+        /// back like for any signal, and the latest one is kept until the interval ends.
+        fn rate_limited_setter<T>(set: WriteSignal<T>, min_interval: Duration) -> Setter
+        where
+            T: for<'de> Deserialize<'de> + 'static,
+        {
+            use leptos::SignalSet;
+
+            let interval = min_interval.as_secs_f64() * 1000.0;
+            let last_set = Rc::new(Cell::new(None::<f64>));
+            let pending = Rc::new(RefCell::new(None::<T>));
+            Rc::new(move |doc| {
+                let value = T::deserialize(doc).map_err(|err| format!("{}: {}", std::any::type_name::<T>(), err))?;
+                let at = now();
+                let elapsed = match (last_set.get(), at) {
+                    (Some(last), Some(at)) => at - last,
+                    _ => f64::INFINITY,
+                };
+                if elapsed >= interval {
+                    pending.borrow_mut().take();
+                    last_set.set(at);
+                    set.set(value);
+                    return Ok(());
+                }
+                // The first value held back sets the signal once the interval ends
+                let scheduled = pending.borrow_mut().replace(value).is_some();
+                #[cfg(target_arch = "wasm32")]
+                if !scheduled {
+                    let last_set = last_set.clone();
+                    let pending = pending.clone();
+                    let flush = move || {
+                        if let Some(value) = pending.borrow_mut().take() {
+                            last_set.set(now());
+                            set.set(value);
+                        }
+                    };
+                    leptos::set_timeout(flush, Duration::from_secs_f64((interval - elapsed).max(0.0) / 1000.0));
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                let _ = scheduled;
+                Ok(())
+            })
+        }
+
+        /// The `performance.now()` of the browser, used to timestamp updates.
+        fn now() -> Option<f64> {
+            cfg_if::cfg_if! {
+                if #[cfg(target_arch = "wasm32")] {
+                    web_sys::window().and_then(|window| window.performance()).map(|performance| performance.now())
+                } else {
+                    // Tests of a `MockSse` on native targets have no browser to ask
+                    use std::time::{SystemTime, UNIX_EPOCH};
+
+                    SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|elapsed| elapsed.as_secs_f64() * 1000.0)
+                }
+            }
+        }
+
+        /// How the version of an update follows the last one applied to its signal.
+        enum VersionCheck {
+            /// The update follows the last one, or cannot be checked.
+            InOrder,
+            /// The update starts a new stream, diffed against the initial value again, as the
+            /// server does after a reconnection it cannot resume.
+            Restarted,
+            /// Updates were missed, or arrived out of order.
+            Missed { expected: u64 },
+        }
+
+        impl ServerSignalEventSourceContext {
+            fn check_version(&self, name: &str, update: &ServerSignalUpdate) -> VersionCheck {
+                if update.version == 0 {
+                    return VersionCheck::InOrder;
+                }
+                let mut versions = self.versions.borrow_mut();
+                let check = match versions.get(name) {
+                    Some(&last) if update.version != last + 1 && update.snapshot.is_none() => {
+                        if update.version == 1 {
+                            VersionCheck::Restarted
+                        } else {
+                            VersionCheck::Missed { expected: last + 1 }
                         }
-                        signal.update(|doc| {
-                            json_patch::patch(doc, &update_signal.patch).unwrap();
-                        });
-                    } else {
-                        leptos::logging::warn!("No local state for update to {}. Queuing patch.", name);
-                        delayed_map.entry(name.clone()).or_default().push(update_signal.patch.clone());
                     }
+                    _ => VersionCheck::InOrder,
+                };
+                // The last version applied is kept after a gap, so the updates following the
+                // missed ones are not taken as in order
+                if !matches!(check, VersionCheck::Missed { .. }) {
+                    versions.insert(name.to_owned().into(), update.version);
                 }
-            }) as Box<dyn FnMut(_)>);
-            let function: &Function = callback.as_ref().unchecked_ref();
-            es.inner.set_onmessage(Some(function));
+                check
+            }
+        }
+
+        /// Reconnects once the current updates have been handled, after updates were missed.
+        ///
+        /// The new stream of the server starts over from the initial value of each signal.
+        fn resync(es: &ServerSignalEventSourceContext) {
+            if es.resync_pending.replace(true) {
+                return;
+            }
+            let es = es.clone();
+            leptos::queue_microtask(move || {
+                es.resync_pending.set(false);
+                if let Err(err) = reopen(&es, &es.source().url()) {
+                    leptos::logging::error!("Failed to reconnect to resync server signals: {:?}", err);
+                }
+            });
+        }
+
+        /// Fetches the value of the signal `name` from the url set by `provide_sse_resync_url`,
+        /// or through `sse_snapshot`, or reconnects to resync every signal if neither was set.
+        fn resync_signal(es: &ServerSignalEventSourceContext, name: &str) {
+            let Some(source) = es.resync_source.borrow().clone() else {
+                resync(es);
+                return;
+            };
+            let name: Cow<'static, str> = Cow::Owned(name.to_owned());
+            let mut requests = es.resync_requests.borrow_mut();
+            if requests.contains_key(&name) {
+                return;
+            }
+            requests.insert(name.clone(), 0);
+            drop(requests);
+            #[cfg(target_arch = "wasm32")]
+            {
+                let es = es.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let result = match &source {
+                        ResyncSource::Url(url) => fetch_snapshot(url, &name).await,
+                        ResyncSource::ServerFn => crate::resync::sse_snapshot(name.to_string())
+                            .await
+                            .map_err(|err| JsValue::from_str(&err.to_string())),
+                    };
+                    finish_resync(&es, name, result);
+                });
+            }
+            // There is no fetch outside the browser, where `MockSse::respond_resync` answers
+            #[cfg(not(target_arch = "wasm32"))]
+            if let ResyncSource::Url(url) = source {
+                let _ = url;
+            }
+        }
+
+        /// Applies the snapshot fetched to resync the signal `name`.
+        ///
+        /// The updates received while it was fetched were dropped, as the snapshot holds them,
+        /// so the next update follows the last of them.
+        fn finish_resync(es: &ServerSignalEventSourceContext, name: Cow<'static, str>, result: Result<ServerSignalUpdate, JsValue>) {
+            let Some(last_version) = es.resync_requests.borrow_mut().remove(&name) else {
+                return;
+            };
+            match result {
+                Ok(update) if update.name == name && update.snapshot.is_some() => {
+                    if last_version != 0 {
+                        es.versions.borrow_mut().insert(name, last_version);
+                    }
+                    apply_updates(es, vec![update]);
+                }
+                Ok(update) => {
+                    leptos::logging::error!("Ignoring resync of {} which is not a snapshot of {}", update.name, name);
+                }
+                Err(err) => {
+                    leptos::logging::error!("Failed to resync server signal {}: {:?}", name, err);
+                }
+            }
+        }
+
+        /// Posts `name` to `url`, and decodes the snapshot it responds with.
+        #[cfg(target_arch = "wasm32")]
+        async fn fetch_snapshot(url: &str, name: &str) -> Result<ServerSignalUpdate, JsValue> {
+            use wasm_bindgen::JsCast;
+            use wasm_bindgen_futures::JsFuture;
+            use web_sys::{RequestInit, Response};
+
+            let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window to fetch from"))?;
+            let init = RequestInit::new();
+            init.set_method("POST");
+            init.set_body(&JsValue::from_str(name));
+            let response: Response = JsFuture::from(window.fetch_with_str_and_init(url, &init)).await?.dyn_into()?;
+            if !response.ok() {
+                return Err(JsValue::from_str(&format!("the server responded with status {}", response.status())));
+            }
+            let text = JsFuture::from(response.text()?).await?.as_string().unwrap_or_default();
+            serde_json::from_str(&text).map_err(|err| JsValue::from_str(&err.to_string()))
+        }
+
+        /// Closes the event source, and opens it again after the delay set by `policy`.
+        fn schedule_reconnect(es: &ServerSignalEventSourceContext, policy: ReconnectPolicy) {
+            // The browser would retry on its own
+            es.source().close();
+            let attempt = es.reconnect_attempts.get();
+            es.reconnect_attempts.set(attempt.saturating_add(1));
+            let delay = policy.delay(attempt, js_sys::Math::random());
+            let context = es.clone();
+            let reconnect = move || {
+                context.reconnect_timer.set(None);
+                if let Err(err) = reopen(&context, &context.source().url()) {
+                    leptos::logging::error!("Failed to reconnect to the SSE: {:?}", err);
+                }
+            };
+            cancel_reconnect(es);
+            match leptos::set_timeout_with_handle(reconnect, delay) {
+                Ok(handle) => es.reconnect_timer.set(Some(handle)),
+                Err(err) => leptos::logging::error!("Failed to schedule reconnecting to the SSE: {:?}", err),
+            }
+        }
+
+        /// Cancels the reconnection scheduled by the reconnect policy, if any.
+        fn cancel_reconnect(es: &ServerSignalEventSourceContext) {
+            if let Some(handle) = es.reconnect_timer.take() {
+                handle.clear();
+            }
+        }
+
+        /// Applies `updates` to `doc`, the value of the signal `name`.
+        ///
+        /// Patches which fail to apply are added to `errors`. Returns `false` if a failed patch
+        /// left `doc` partially patched, in which case it must not be set, and the signal is
+        /// resynced instead.
+        fn patch_value<'a>(
+            es: &ServerSignalEventSourceContext,
+            name: &str,
+            doc: &mut Value,
+            updates: impl IntoIterator<Item = &'a ServerSignalUpdate>,
+            errors: &mut Vec<AppliedPatchError>,
+        ) -> bool {
+            for update in updates {
+                match es.check_version(name, update) {
+                    VersionCheck::InOrder => {}
+                    VersionCheck::Restarted => {
+                        if let Some(initial) = es.initial_values.borrow().get(name) {
+                            *doc = initial.clone();
+                        }
+                    }
+                    VersionCheck::Missed { expected } => {
+                        leptos::logging::warn!(
+                            "Missed updates to {}: expected version {}, received {}. Resyncing.",
+                            name,
+                            expected,
+                            update.version
+                        );
+                        resync_signal(es, name);
+                        // The value fetched holds the update which revealed the gap
+                        if let Some(last_version) = es.resync_requests.borrow_mut().get_mut(name) {
+                            *last_version = update.version;
+                        }
+                        return true;
+                    }
+                }
+                // A failed patch must not stop the updates of any other signal
+                if let Err(err) = update.apply(doc) {
+                    let error = AppliedPatchError {
+                        name: name.to_owned().into(),
+                        patch: update.patch.clone(),
+                        kind: AppliedPatchErrorKind::Patch,
+                        message: err.to_string(),
+                    };
+                    leptos::logging::error!("{}", error);
+                    errors.push(error);
+                    // Patches applied in place are not undone, so the value may be partially
+                    // patched, and the next patches would not apply either. Otherwise the
+                    // failed patch left the value untouched.
+                    if cfg!(feature = "in-place-patch") {
+                        resync_signal(es, name);
+                        return false;
+                    }
+                    if es.resync_source.borrow().is_some() {
+                        resync_signal(es, name);
+                    }
+                }
+            }
+            true
+        }
+
+        /// The number of errors kept by `sse_error_log`.
+        const ERROR_LOG_CAPACITY: usize = 100;
+
+        impl ServerSignalEventSourceContext {
+            fn log_errors(&self, errors: Vec<AppliedPatchError>) {
+                use leptos::SignalUpdate;
+
+                if errors.is_empty() {
+                    return;
+                }
+                self.error_log.update(|log| {
+                    log.extend(errors);
+                    let excess = log.len().saturating_sub(ERROR_LOG_CAPACITY);
+                    log.drain(..excess);
+                });
+            }
+        }
+
+        /// Applies `updates` to `signal`, the json value of the signal `name`, and sets the
+        /// signal read by components.
+        ///
+        /// In debug builds, the updates are applied to a copy of the value, and rolled back if
+        /// it no longer deserializes. With the `in-place-patch` feature, they are always applied
+        /// to the value itself, which is left unset if a patch failed part way.
+        fn update_signal<'a>(
+            es: &ServerSignalEventSourceContext,
+            name: Cow<'static, str>,
+            signal: RwSignal<Value>,
+            updates: impl IntoIterator<Item = &'a ServerSignalUpdate>,
+        ) {
+            use leptos::{SignalGetUntracked, SignalSet, SignalUpdate, SignalUpdateUntracked, SignalWithUntracked};
+
+            let Some(setters) = es.setters.borrow().get(&name).cloned() else {
+                return;
+            };
+            // Every signal and field created for `name` shares its json value
+            let setter = |doc: &Value| setters.iter().try_for_each(|setter| setter(doc));
+            let updates: Vec<&ServerSignalUpdate> = updates.into_iter().collect();
+            #[cfg(feature = "debug-logging")]
+            for update in &updates {
+                leptos::logging::log!("Applying update to {}: {}", name, serde_json::to_string(update).unwrap_or_default());
+            }
+            let deserialize_error = |message: String| AppliedPatchError {
+                name: name.clone(),
+                patch: Patch(updates.iter().flat_map(|update| update.patch.0.iter().cloned()).collect()),
+                kind: AppliedPatchErrorKind::Deserialize,
+                message,
+            };
+            // Logged once the signal is no longer borrowed, as the log may be read by effects
+            let mut errors = Vec::new();
+            if cfg!(all(debug_assertions, not(feature = "in-place-patch"))) {
+                // Patch a copy, so the signal is left untouched if it no longer matches its type.
+                // A failed patch leaves the copy untouched too.
+                let mut doc = signal.get_untracked();
+                // Restored along with the value, so the updates rolled back are not taken as applied
+                let version = es.versions.borrow().get(&name).copied();
+                if !patch_value(es, &name, &mut doc, updates.iter().copied(), &mut errors) {
+                    es.log_errors(errors);
+                    return;
+                }
+                if let Err(err) = setter(&doc) {
+                    leptos::logging::error!(
+                        "Rolled back update to {}, as the patched value is not a valid {}\nPatched value: {}",
+                        name,
+                        err,
+                        doc
+                    );
+                    errors.push(deserialize_error(err));
+                    match version {
+                        Some(version) => es.versions.borrow_mut().insert(name.clone(), version),
+                        None => es.versions.borrow_mut().remove(&name),
+                    };
+                    es.log_errors(errors);
+                    return;
+                }
+                signal.set(doc);
+            } else {
+                // Only notified once patched, as a value left partially patched is not set
+                let patched = signal
+                    .try_update_untracked(|doc| patch_value(es, &name, doc, updates.iter().copied(), &mut errors))
+                    .unwrap_or(false);
+                if !patched {
+                    es.log_errors(errors);
+                    return;
+                }
+                signal.update(|_| {});
+                if let Err(err) = signal.with_untracked(|doc| setter(doc)) {
+                    let error = deserialize_error(err);
+                    leptos::logging::error!("{}", error);
+                    errors.push(error);
+                }
+            }
+            es.log_errors(errors);
+            es.mark_updated(name);
+        }
+
+        /// Applies `updates` of a single signal, or queues them until the signal is created.
+        fn apply_updates(es: &ServerSignalEventSourceContext, updates: Vec<ServerSignalUpdate>) {
+            let Some(name) = updates.first().map(|update| update.name.clone()) else {
+                return;
+            };
+            // The value being fetched to resync the signal holds these updates, which may not
+            // follow the value the signal has
+            if let Some(last_version) = es.resync_requests.borrow_mut().get_mut(&name) {
+                if let Some(version) = updates.iter().rev().map(|update| update.version).find(|version| *version != 0) {
+                    *last_version = version;
+                }
+                return;
+            }
+            // Setting the signals runs effects, which may create other server signals
+            let signal = es.state_signals.borrow().get(&name).copied();
+            let paused = es.paused.get();
+            let mut delayed_map = es.delayed_updates.borrow_mut();
+            if let Some(signal) = signal.filter(|_| !paused) {
+                let delayed_updates = delayed_map.remove(&name).unwrap_or_default();
+                drop(delayed_map);
+                update_signal(es, name, signal, delayed_updates.iter().chain(&updates));
+            } else {
+                // Updates are queued the same way while paused, until `sse_resume` is called
+                if !paused {
+                    leptos::logging::warn!("No local state for update to {}. Queuing patch.", name);
+                }
+                let delayed_updates = delayed_map.entry(name).or_default();
+                for update in updates {
+                    // A snapshot supersedes any update queued before it
+                    if update.snapshot.is_some() {
+                        delayed_updates.clear();
+                    }
+                    delayed_updates.push(update);
+                }
+            }
+        }
+
+        /// Applies the updates queued while paused to the signals which were created.
+        fn flush_paused(es: &ServerSignalEventSourceContext) {
+            // Setting the signals runs effects, which may create other server signals
+            let queued: Vec<_> = {
+                let state_signals = es.state_signals.borrow();
+                let mut delayed_map = es.delayed_updates.borrow_mut();
+                let names: Vec<_> = delayed_map.keys().filter(|name| state_signals.contains_key(*name)).cloned().collect();
+                names
+                    .into_iter()
+                    .filter_map(|name| {
+                        let updates = delayed_map.remove(&name)?;
+                        Some((state_signals[&name], name, updates))
+                    })
+                    .collect()
+            };
+            #[cfg(feature = "raw")]
+            let queued_raw: Vec<_> = es.paused_raw.borrow_mut().drain().collect();
+            leptos::batch(|| {
+                for (signal, name, updates) in queued {
+                    update_signal(es, name, signal, &updates);
+                }
+                #[cfg(feature = "raw")]
+                for (name, data) in queued_raw {
+                    let setter = es.raw_setters.borrow().get(&name).cloned();
+                    if let Some(setter) = setter {
+                        apply_raw(es, name, &setter, &data);
+                    }
+                }
+            });
+        }
 
-            // Keep the closure alive for the lifetime of the program
-            callback.forget();
+        /// Replaces the event source with a new one connected to `url`.
+        fn reopen(es: &ServerSignalEventSourceContext, url: &str) -> Result<(), JsValue> {
+            use leptos::SignalSet;
 
+            cancel_reconnect(es);
+            #[cfg(feature = "testing")]
+            if let Source::Mock(source) = es.source() {
+                source.reconnect();
+                handle_open(es);
+                return Ok(());
+            }
+            let source = Source::open(url, &es.settings.borrow())?;
+            detach(es);
+            es.source().close();
+            es.connection_state.set(ConnectionState::from_ready_state(source.ready_state()));
+            *es.inner.borrow_mut() = source.clone();
+            source.provide(es.owner);
+            attach(es);
             Ok(())
         }
-    } else {
+
+        #[cfg(target_arch = "wasm32")]
         #[inline]
-        fn provide_sse_inner(_url: &str) -> Result<(), JsValue> {
+        fn provide_sse_inner(url: &str, settings: SseOptions) -> Result<(), JsValue> {
+            use leptos::use_context;
+
+            match use_context::<ServerSignalEventSourceContext>() {
+                // A connection which was closed through `close_sse` is replaced by a new one
+                Some(es) if es.source().ready_state() != EventSource::CLOSED => {
+                    detach(&es);
+                    *es.settings.borrow_mut() = settings;
+                    attach(&es);
+                }
+                _ => {
+                    let source = Source::open(url, &settings)?;
+                    source.provide(None);
+                    let es = ServerSignalEventSourceContext::new(source, settings);
+                    provide_context(es.clone());
+                    attach(&es);
+                }
+            }
             Ok(())
         }
+
+        /// Removes the listeners of the event source, including those for each server signal.
+        fn detach(es: &ServerSignalEventSourceContext) {
+            let source = es.source();
+            if let Some(handler) = es.message_handler.borrow_mut().take() {
+                for event_type in message_event_types(es) {
+                    if let Err(err) = source.remove_event_listener_with_callback(&event_type, &handler) {
+                        leptos::logging::error!("Failed to stop listening for {} events: {:?}", event_type, err);
+                    }
+                }
+                for name in es.signal_names() {
+                    unlisten_signal(&source, &name, &handler);
+                }
+            }
+            for (event_type, handler) in es.listeners.borrow_mut().drain(..) {
+                if let Err(err) = source.remove_event_listener_with_callback(event_type, &handler) {
+                    leptos::logging::error!("Failed to stop listening for {} events: {:?}", event_type, err);
+                }
+            }
+            es.closures.borrow_mut().clear();
+        }
+
+        /// The events which may carry the updates of any server signal: `message` events, and
+        /// those given to `SseOptions::with_events`.
+        fn message_event_types(es: &ServerSignalEventSourceContext) -> Vec<String> {
+            let mut event_types = vec!["message".to_owned()];
+            event_types.extend(es.settings.borrow().events.iter().cloned());
+            event_types
+        }
+
+        /// Registers `handler` as the listener for the updates of the server signal `name`, which
+        /// are sent as events named after it, or after `compressed_event_type` once compressed.
+        fn listen_signal(source: &Source, name: &str, handler: &Function) {
+            for event_type in [Cow::Borrowed(name), Cow::Owned(crate::codec::compressed_event_type(name))] {
+                if let Err(err) = source.add_event_listener_with_callback(&event_type, handler) {
+                    leptos::logging::error!("Failed to listen for server signal {}: {:?}", name, err);
+                }
+            }
+        }
+
+        /// Removes the listeners registered by `listen_signal`.
+        fn unlisten_signal(source: &Source, name: &str, handler: &Function) {
+            for event_type in [Cow::Borrowed(name), Cow::Owned(crate::codec::compressed_event_type(name))] {
+                if let Err(err) = source.remove_event_listener_with_callback(&event_type, handler) {
+                    leptos::logging::error!("Failed to stop listening for server signal {}: {:?}", name, err);
+                }
+            }
+        }
+
+        /// Registers `handler` as the listener for `event_type` events, until `detach` is called.
+        fn listen(es: &ServerSignalEventSourceContext, source: &Source, event_type: &'static str, handler: &Function) {
+            if let Err(err) = source.add_event_listener_with_callback(event_type, handler) {
+                leptos::logging::error!("Failed to listen for {} events: {:?}", event_type, err);
+            }
+            es.listeners.borrow_mut().push((event_type, handler.clone()));
+        }
+
+        /// Decodes the data of a `message` event, or of an event named after a server signal,
+        /// and applies the updates it carries.
+        fn handle_message(es: &ServerSignalEventSourceContext, event_type: &str, data: &str) {
+            // Compressed updates are sent as events of their own, for the same signal
+            let event_type = crate::codec::compressed_event_signal(event_type, data).unwrap_or(event_type);
+            #[cfg(feature = "raw")]
+            {
+                let raw_setter = es.raw_setters.borrow().get(event_type).cloned();
+                if raw_setter.is_some() && es.paused.get() {
+                    // Each value replaces the previous one, so only the last one is kept
+                    es.paused_raw.borrow_mut().insert(Cow::Owned(event_type.to_owned()), data.to_owned());
+                    return;
+                }
+                if let Some(setter) = raw_setter {
+                    apply_raw(es, Cow::Owned(event_type.to_owned()), &setter, data);
+                    return;
+                }
+            }
+            let codec = es.settings.borrow().codec.clone();
+            let message = match codec {
+                None => ServerSignalMessage::decode(data, None),
+                Some(codec) => match codec.builtin() {
+                    Some(builtin) => ServerSignalMessage::decode(data, Some(builtin)),
+                    None => codec.decode(data).map(ServerSignalMessage::Update),
+                },
+            };
+            let any_signal = event_type == "message" || es.settings.borrow().events.iter().any(|event| event == event_type);
+            match message {
+                // Named events must match the signal they update
+                Ok(ServerSignalMessage::Update(update)) if !any_signal && event_type != update.name => {
+                    leptos::logging::warn!("Ignoring update to {} sent as event {}", update.name, event_type);
+                }
+                Ok(ServerSignalMessage::Update(update)) => {
+                    deliver(es, vec![update]);
+                }
+                Ok(ServerSignalMessage::Batch(_)) if !any_signal => {
+                    leptos::logging::warn!("Ignoring batch of updates sent as event {}", event_type);
+                }
+                Ok(ServerSignalMessage::Batch(batch)) => {
+                    deliver(es, batch.updates);
+                }
+                Err(err) => {
+                    leptos::logging::warn!("Failed to decode server signal update: {}", err);
+                }
+            }
+        }
+
+        /// Applies the updates of an event, or queues them until the batching window of the
+        /// connection ends.
+        fn deliver(es: &ServerSignalEventSourceContext, updates: Vec<ServerSignalUpdate>) {
+            let batching = es.settings.borrow().batching;
+            if batching == UpdateBatching::Immediate {
+                apply_batch(es, updates);
+                return;
+            }
+            let mut queued = es.queued_updates.borrow_mut();
+            // The window starts with the first update queued
+            let schedule = queued.is_empty() && !updates.is_empty();
+            queued.extend(updates);
+            drop(queued);
+            if !schedule {
+                return;
+            }
+            let es = es.clone();
+            let flush = move || {
+                let updates = std::mem::take(&mut *es.queued_updates.borrow_mut());
+                apply_batch(&es, updates);
+            };
+            match batching {
+                UpdateBatching::Window(window) => leptos::set_timeout(flush, window),
+                _ => leptos::request_animation_frame(flush),
+            }
+        }
+
+        /// Applies `updates` together, updating each signal once.
+        fn apply_batch(es: &ServerSignalEventSourceContext, updates: Vec<ServerSignalUpdate>) {
+            // Group the updates by signal, keeping the order of the updates of each
+            let mut grouped: Vec<(Cow<'static, str>, Vec<ServerSignalUpdate>)> = Vec::new();
+            for update in updates {
+                match grouped.iter_mut().find(|(name, _)| *name == update.name) {
+                    Some((_, updates)) => updates.push(update),
+                    None => grouped.push((update.name.clone(), vec![update])),
+                }
+            }
+            leptos::batch(|| {
+                for (_, updates) in grouped {
+                    apply_updates(es, updates);
+                }
+            });
+        }
+
+        /// Decodes the raw value of the signal `name`, and sets the signal.
+        #[cfg(feature = "raw")]
+        fn apply_raw(es: &ServerSignalEventSourceContext, name: Cow<'static, str>, setter: &RawSetter, data: &str) {
+            let result = crate::codec::decode_raw(data)
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| setter(&bytes));
+            match result {
+                Ok(()) => es.mark_updated(name),
+                Err(message) => {
+                    let error = AppliedPatchError {
+                        name,
+                        patch: Patch(Vec::new()),
+                        kind: AppliedPatchErrorKind::Deserialize,
+                        message,
+                    };
+                    leptos::logging::error!("{}", error);
+                    es.log_errors(vec![error]);
+                }
+            }
+        }
+
+        /// Updates the state of the connection once it is open, and calls the handlers
+        /// registered with `on_sse_open`.
+        fn handle_open(es: &ServerSignalEventSourceContext) {
+            use leptos::{with_owner, SignalSet, SignalUpdate};
+
+            es.reconnect_attempts.set(0);
+            es.connection_state.set(ConnectionState::Open);
+            if es.has_opened.replace(true) {
+                es.reconnect_count.update(|count| *count += 1);
+            }
+            // Handlers may register other handlers, so they are called without a borrow
+            let handlers = es.open_handlers.borrow().clone();
+            let call_handlers = || {
+                for handler in handlers {
+                    handler();
+                }
+            };
+            match es.owner {
+                Some(owner) => with_owner(owner, call_handlers),
+                None => call_handlers(),
+            }
+        }
+
+        /// Decodes the data of an `error` event sent by the server.
+        fn handle_server_error(es: &ServerSignalEventSourceContext, data: &str) {
+            use leptos::SignalSet;
+
+            match serde_json::from_str::<ServerSignalError>(data) {
+                Ok(error) => {
+                    leptos::logging::error!("{}", error);
+                    es.last_error.set(Some(error));
+                }
+                Err(err) => {
+                    leptos::logging::warn!("Failed to decode server signal error: {}", err);
+                }
+            }
+        }
+
+        /// Sets up the listeners of the event source, including those for each server signal.
+        fn attach(es: &ServerSignalEventSourceContext) {
+            use web_sys::{Event, MessageEvent};
+            use wasm_bindgen::JsCast;
+            use leptos::{with_owner, SignalSet};
+
+            let source = es.source();
+            let context = es.clone();
+            let callback = Closure::wrap(Box::new(move |event: Event| {
+                let event: MessageEvent = event.unchecked_into();
+                let Some(ws_string) = event.data().as_string() else {
+                    leptos::logging::warn!("Ignoring server signal event without text data");
+                    return;
+                };
+                handle_message(&context, &event.type_(), &ws_string);
+            }) as Box<dyn FnMut(_)>);
+            let function: &Function = callback.as_ref().unchecked_ref();
+            for event_type in message_event_types(es) {
+                if let Err(err) = source.add_event_listener_with_callback(&event_type, function) {
+                    leptos::logging::error!("Failed to listen for {} events: {:?}", event_type, err);
+                }
+            }
+            for name in es.signal_names() {
+                listen_signal(&source, &name, function);
+            }
+            *es.message_handler.borrow_mut() = Some(function.clone());
+
+            let connection_state = es.connection_state;
+            let context = es.clone();
+            let on_open = Closure::wrap(Box::new(move |_: Event| {
+                handle_open(&context);
+            }) as Box<dyn FnMut(_)>);
+            listen(es, &source, "open", on_open.as_ref().unchecked_ref());
+
+            // A finite stream ends with a close event, after which the browser must not reconnect
+            let inner = source.clone();
+            let on_close = Closure::wrap(Box::new(move |_: Event| {
+                inner.close();
+                // Closing the event source does not fire an error event
+                connection_state.set(ConnectionState::Closed);
+            }) as Box<dyn FnMut(_)>);
+            listen(es, &source, "close", on_close.as_ref().unchecked_ref());
+
+            let inner = source.clone();
+            let context = es.clone();
+            let error_handlers = es.error_handlers.clone();
+            let owner = es.owner;
+            let on_error = Closure::wrap(Box::new(move |event: Event| {
+                // Error events sent by the server carry data, unlike connection errors
+                if let Some(event) = event.dyn_ref::<MessageEvent>() {
+                    handle_server_error(&context, &event.data().as_string().unwrap_or_default());
+                    return;
+                }
+
+                let reconnect = context.settings.borrow().reconnect;
+                match reconnect {
+                    Some(policy) => {
+                        schedule_reconnect(&context, policy);
+                        connection_state.set(ConnectionState::Connecting);
+                    }
+                    // The browser reconnects on its own unless the connection was closed for good
+                    None => connection_state.set(ConnectionState::from_ready_state(inner.ready_state())),
+                }
+
+                // Handlers may register other handlers, so they are called without a borrow.
+                // They run with the owner of the context, so they can use it too.
+                let handlers = error_handlers.borrow().clone();
+                let call_handlers = || {
+                    for handler in handlers {
+                        handler(event.clone());
+                    }
+                };
+                match owner {
+                    Some(owner) => with_owner(owner, call_handlers),
+                    None => call_handlers(),
+                }
+            }) as Box<dyn FnMut(_)>);
+            listen(es, &source, "error", on_error.as_ref().unchecked_ref());
+
+            // Keep the closures alive until the listeners are removed
+            es.closures.borrow_mut().extend([callback, on_open, on_close, on_error]);
+        }
+    }
+}
+
+// There is no browser to connect from on the server, nor in native tests, which get a
+// `MockSse` to push updates into instead
+#[cfg(not(target_arch = "wasm32"))]
+#[inline]
+fn provide_sse_inner(_url: &str, settings: SseOptions) -> Result<(), JsValue> {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "testing")] {
+            crate::testing::MockSse::provide_with_settings(settings);
+        } else {
+            let _ = settings;
+            leptos::provide_context(NoopSse);
+        }
     }
+    Ok(())
 }
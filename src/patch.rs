@@ -0,0 +1,425 @@
+//! Applies json patches in place, for the `in-place-patch` feature.
+//!
+//! Unlike [`json_patch::patch`], this keeps no log of the replaced values to undo a failed
+//! patch, and assigns the new values into the existing ones, reusing their allocations where
+//! both have the same shape. A failed patch leaves the value partially patched.
+
+use std::borrow::Cow;
+use std::fmt::{self, Display};
+
+use json_patch::{PatchErrorKind, PatchOperation};
+use serde_json::Value;
+
+/// An operation of a patch which failed, after the operations before it were applied.
+#[derive(Debug)]
+pub(crate) struct PatchError {
+    operation: usize,
+    path: String,
+    kind: PatchErrorKind,
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Operation '/{}' failed at path '{}': {}",
+            self.operation, self.path, self.kind
+        )
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Applies the operations of `patch` to `doc` in order.
+pub(crate) fn patch(doc: &mut Value, patch: &[PatchOperation]) -> Result<(), PatchError> {
+    for (operation, op) in patch.iter().enumerate() {
+        let (path, result) = match op {
+            PatchOperation::Add(op) => (&op.path, add(doc, &op.path, &op.value)),
+            PatchOperation::Remove(op) => (&op.path, remove(doc, &op.path).map(drop)),
+            PatchOperation::Replace(op) => (&op.path, replace(doc, &op.path, &op.value)),
+            PatchOperation::Move(op) => (&op.path, mov(doc, &op.from, &op.path)),
+            PatchOperation::Copy(op) => (&op.path, copy(doc, &op.from, &op.path)),
+            PatchOperation::Test(op) => (&op.path, test(doc, &op.path, &op.value)),
+        };
+        result.map_err(|kind| PatchError {
+            operation,
+            path: path.clone(),
+            kind,
+        })?;
+    }
+    Ok(())
+}
+
+/// Sets `target` to `source`, reusing the strings, arrays and objects of `target` which
+/// `source` has too.
+fn assign(target: &mut Value, source: &Value) {
+    match (target, source) {
+        (Value::String(target), Value::String(source)) => target.clone_from(source),
+        (Value::Array(target), Value::Array(source)) => {
+            target.truncate(source.len());
+            for (target, source) in target.iter_mut().zip(source) {
+                assign(target, source);
+            }
+            let len = target.len();
+            target.extend(source[len..].iter().cloned());
+        }
+        (Value::Object(target), Value::Object(source)) => {
+            target.retain(|key, _| source.contains_key(key));
+            for (key, source) in source {
+                match target.get_mut(key) {
+                    Some(target) => assign(target, source),
+                    None => {
+                        target.insert(key.clone(), source.clone());
+                    }
+                }
+            }
+        }
+        (target, source) => *target = source.clone(),
+    }
+}
+
+fn unescape(token: &str) -> Cow<'_, str> {
+    if token.contains('~') {
+        Cow::Owned(token.replace("~1", "/").replace("~0", "~"))
+    } else {
+        Cow::Borrowed(token)
+    }
+}
+
+/// Parses the index of an array of `len` elements, without the leading zeroes RFC 6901 forbids.
+fn parse_index(token: &str, len: usize) -> Result<usize, PatchErrorKind> {
+    if (token.starts_with('0') && token.len() != 1) || token.starts_with('+') {
+        return Err(PatchErrorKind::InvalidPointer);
+    }
+    match token.parse::<usize>() {
+        Ok(index) if index < len => Ok(index),
+        _ => Err(PatchErrorKind::InvalidPointer),
+    }
+}
+
+/// Splits `path` into the pointer to the parent and the last token.
+fn split_pointer(path: &str) -> Result<(&str, &str), PatchErrorKind> {
+    path.rfind('/')
+        .map(|index| (&path[..index], &path[index + 1..]))
+        .ok_or(PatchErrorKind::InvalidPointer)
+}
+
+fn add(doc: &mut Value, path: &str, value: &Value) -> Result<(), PatchErrorKind> {
+    if path.is_empty() {
+        assign(doc, value);
+        return Ok(());
+    }
+    let (parent, last) = split_pointer(path)?;
+    match doc.pointer_mut(parent) {
+        // Adding an existing member replaces it, so its value can be reused
+        Some(Value::Object(object)) => match object.get_mut(unescape(last).as_ref()) {
+            Some(target) => assign(target, value),
+            None => {
+                object.insert(unescape(last).into_owned(), value.clone());
+            }
+        },
+        _ => insert(doc, path, value.clone())?,
+    }
+    Ok(())
+}
+
+/// Adds `value` at `path`, which is moved or copied there.
+fn insert(doc: &mut Value, path: &str, value: Value) -> Result<(), PatchErrorKind> {
+    if path.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    let (parent, last) = split_pointer(path)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(object)) => {
+            object.insert(unescape(last).into_owned(), value);
+        }
+        Some(Value::Array(array)) if last == "-" => array.push(value),
+        Some(Value::Array(array)) => {
+            let index = parse_index(last, array.len() + 1)?;
+            array.insert(index, value);
+        }
+        _ => return Err(PatchErrorKind::InvalidPointer),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut Value, path: &str) -> Result<Value, PatchErrorKind> {
+    let (parent, last) = split_pointer(path)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(object)) => object
+            .remove(unescape(last).as_ref())
+            .ok_or(PatchErrorKind::InvalidPointer),
+        Some(Value::Array(array)) => {
+            let index = parse_index(last, array.len())?;
+            Ok(array.remove(index))
+        }
+        _ => Err(PatchErrorKind::InvalidPointer),
+    }
+}
+
+fn replace(doc: &mut Value, path: &str, value: &Value) -> Result<(), PatchErrorKind> {
+    let target = doc
+        .pointer_mut(path)
+        .ok_or(PatchErrorKind::InvalidPointer)?;
+    assign(target, value);
+    Ok(())
+}
+
+fn mov(doc: &mut Value, from: &str, path: &str) -> Result<(), PatchErrorKind> {
+    if path.starts_with(from) && path[from.len()..].starts_with('/') {
+        return Err(PatchErrorKind::CannotMoveInsideItself);
+    }
+    let value = remove(doc, from).map_err(|err| match err {
+        PatchErrorKind::InvalidPointer => PatchErrorKind::InvalidFromPointer,
+        err => err,
+    })?;
+    insert(doc, path, value)
+}
+
+fn copy(doc: &mut Value, from: &str, path: &str) -> Result<(), PatchErrorKind> {
+    let value = doc
+        .pointer(from)
+        .ok_or(PatchErrorKind::InvalidFromPointer)?
+        .clone();
+    insert(doc, path, value)
+}
+
+fn test(doc: &Value, path: &str, expected: &Value) -> Result<(), PatchErrorKind> {
+    match doc.pointer(path) {
+        Some(value) if value == expected => Ok(()),
+        Some(_) => Err(PatchErrorKind::TestFailed),
+        None => Err(PatchErrorKind::InvalidPointer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Applies `operations` to `doc` with both this module and [`json_patch::patch`], which must
+    /// agree on the result, or on the operation which failed and why.
+    fn check(doc: Value, operations: Value) -> Result<Value, String> {
+        let operations: Vec<PatchOperation> = serde_json::from_value(operations).unwrap();
+        let mut in_place = doc.clone();
+        let mut expected = doc;
+        let result = patch(&mut in_place, &operations)
+            .map(|()| in_place)
+            .map_err(|err| format!("{}: {}: {}", err.operation, err.path, err.kind));
+        let expected = json_patch::patch(&mut expected, &operations)
+            .map(|()| expected)
+            .map_err(|err| format!("{}: {}: {}", err.operation, err.path, err.kind));
+        assert_eq!(result, expected);
+        result
+    }
+
+    #[test]
+    fn adds() {
+        let doc = json!({ "a": [1, 2], "b": { "c": "d" } });
+        let patched = check(
+            doc.clone(),
+            json!([
+                { "op": "add", "path": "/b/e", "value": 3 },
+                { "op": "add", "path": "/b/c", "value": ["x"] },
+                { "op": "add", "path": "/a/1", "value": 4 },
+                { "op": "add", "path": "/a/3", "value": 5 },
+            ]),
+        );
+        assert_eq!(
+            patched.unwrap(),
+            json!({ "a": [1, 4, 2, 5], "b": { "c": ["x"], "e": 3 } })
+        );
+        let whole = check(doc, json!([{ "op": "add", "path": "", "value": [true] }]));
+        assert_eq!(whole.unwrap(), json!([true]));
+    }
+
+    #[test]
+    fn removes() {
+        let patched = check(
+            json!({ "a": [1, 2, 3], "b": { "c": "d", "e": null } }),
+            json!([
+                { "op": "remove", "path": "/a/0" },
+                { "op": "remove", "path": "/b/c" },
+            ]),
+        );
+        assert_eq!(patched.unwrap(), json!({ "a": [2, 3], "b": { "e": null } }));
+    }
+
+    #[test]
+    fn replaces() {
+        let patched = check(
+            json!({ "a": { "b": [1, 2, 3], "c": "d" }, "e": 1 }),
+            json!([
+                { "op": "replace", "path": "/a", "value": { "b": [4], "f": "g" } },
+                { "op": "replace", "path": "/e", "value": "h" },
+            ]),
+        );
+        assert_eq!(
+            patched.unwrap(),
+            json!({ "a": { "b": [4], "f": "g" }, "e": "h" })
+        );
+        let whole = check(
+            json!([1]),
+            json!([{ "op": "replace", "path": "", "value": 2 }]),
+        );
+        assert_eq!(whole.unwrap(), json!(2));
+    }
+
+    #[test]
+    fn moves_and_copies() {
+        let patched = check(
+            json!({ "a": [1, 2, 3], "b": { "c": "d" } }),
+            json!([
+                { "op": "move", "from": "/a/0", "path": "/a/2" },
+                { "op": "copy", "from": "/b", "path": "/a/0" },
+                { "op": "move", "from": "/b/c", "path": "/e" },
+                { "op": "copy", "from": "/e", "path": "/b/f" },
+            ]),
+        );
+        assert_eq!(
+            patched.unwrap(),
+            json!({ "a": [{ "c": "d" }, 2, 3, 1], "b": { "f": "d" }, "e": "d" })
+        );
+        // Moving a value to its own path keeps it
+        let same = check(
+            json!({ "a": 1 }),
+            json!([{ "op": "move", "from": "/a", "path": "/a" }]),
+        );
+        assert_eq!(same.unwrap(), json!({ "a": 1 }));
+    }
+
+    #[test]
+    fn tests_values() {
+        let doc = json!({ "a": [1, { "b": null }] });
+        let passed = check(
+            doc.clone(),
+            json!([{ "op": "test", "path": "/a", "value": [1, { "b": null }] }]),
+        );
+        assert_eq!(passed.unwrap(), doc);
+        let failed = check(
+            doc.clone(),
+            json!([{ "op": "test", "path": "/a/0", "value": 2 }]),
+        );
+        assert!(failed.is_err());
+        let missing = check(doc, json!([{ "op": "test", "path": "/c", "value": 2 }]));
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn pointers_are_unescaped() {
+        let patched = check(
+            json!({ "a/b": 1, "c~d": 2, "~1": 3 }),
+            json!([
+                { "op": "replace", "path": "/a~1b", "value": 4 },
+                { "op": "remove", "path": "/c~0d" },
+                // `~01` is an escaped `~` followed by `1`, not a `/`
+                { "op": "add", "path": "/~01", "value": 5 },
+                { "op": "move", "from": "/a~1b", "path": "/e~0~1" },
+            ]),
+        );
+        assert_eq!(patched.unwrap(), json!({ "~1": 5, "e~/": 4 }));
+    }
+
+    #[test]
+    fn dash_is_the_end_of_an_array() {
+        let patched = check(
+            json!({ "a": [1, 2], "b": [] }),
+            json!([
+                { "op": "add", "path": "/a/-", "value": 3 },
+                { "op": "copy", "from": "/a/0", "path": "/b/-" },
+                { "op": "move", "from": "/a/1", "path": "/b/-" },
+            ]),
+        );
+        assert_eq!(patched.unwrap(), json!({ "a": [1, 3], "b": [1, 2] }));
+        // There is no element at the end to remove or move
+        let removed = check(json!([1]), json!([{ "op": "remove", "path": "/-" }]));
+        assert!(removed.is_err());
+        let moved = check(
+            json!([1]),
+            json!([{ "op": "move", "from": "/-", "path": "/0" }]),
+        );
+        assert!(moved.is_err());
+        // An object may have a member named `-`
+        let member = check(
+            json!({}),
+            json!([{ "op": "add", "path": "/-", "value": 1 }]),
+        );
+        assert_eq!(member.unwrap(), json!({ "-": 1 }));
+        let replaced = check(
+            json!([1]),
+            json!([{ "op": "replace", "path": "/-", "value": 2 }]),
+        );
+        assert!(replaced.is_err());
+    }
+
+    #[test]
+    fn indexes_with_leading_zeroes_are_rejected() {
+        let doc = json!([1, 2, 3]);
+        for path in ["/01", "/00", "/+1", "/-1", "/3"] {
+            let removed = check(doc.clone(), json!([{ "op": "remove", "path": path }]));
+            assert!(removed.is_err(), "{path}");
+        }
+        for path in ["/01", "/+0", "/4"] {
+            let added = check(
+                doc.clone(),
+                json!([{ "op": "add", "path": path, "value": 0 }]),
+            );
+            assert!(added.is_err(), "{path}");
+        }
+        let added = check(doc, json!([{ "op": "add", "path": "/3", "value": 0 }]));
+        assert_eq!(added.unwrap(), json!([1, 2, 3, 0]));
+    }
+
+    #[test]
+    fn cannot_move_into_itself() {
+        let doc = json!({ "a": { "b": {} }, "ab": 1 });
+        let inside = check(
+            doc.clone(),
+            json!([{ "op": "move", "from": "/a", "path": "/a/b/c" }]),
+        );
+        assert!(inside.is_err());
+        // A member whose name starts with the same characters is not inside it
+        let sibling = check(doc, json!([{ "op": "move", "from": "/a", "path": "/ab" }]));
+        assert_eq!(sibling.unwrap(), json!({ "ab": { "b": {} } }));
+    }
+
+    #[test]
+    fn errors_name_the_operation_which_failed() {
+        let doc = json!({ "a": [1], "b": "c" });
+        for operation in [
+            json!({ "op": "add", "path": "/x/y", "value": 1 }),
+            json!({ "op": "add", "path": "/b/0", "value": 1 }),
+            json!({ "op": "add", "path": "a", "value": 1 }),
+            json!({ "op": "remove", "path": "" }),
+            json!({ "op": "remove", "path": "/x" }),
+            json!({ "op": "replace", "path": "/a/1", "value": 1 }),
+            json!({ "op": "move", "from": "/x", "path": "/y" }),
+            json!({ "op": "move", "from": "/a/0", "path": "/x/y" }),
+            json!({ "op": "copy", "from": "/x", "path": "/y" }),
+            json!({ "op": "copy", "from": "/b", "path": "/a/2" }),
+        ] {
+            let operations = json!([{ "op": "test", "path": "/b", "value": "c" }, operation]);
+            let error = check(doc.clone(), operations).unwrap_err();
+            assert!(error.starts_with("1: "), "{error}");
+        }
+    }
+
+    #[test]
+    fn a_failed_patch_keeps_the_operations_before_it() {
+        let mut doc = json!({ "a": 1 });
+        let operations: Vec<PatchOperation> = serde_json::from_value(json!([
+            { "op": "replace", "path": "/a", "value": 2 },
+            { "op": "remove", "path": "/b" },
+        ]))
+        .unwrap();
+        let error = patch(&mut doc, &operations).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Operation '/1' failed at path '/b': path is invalid"
+        );
+        // Unlike `json_patch::patch`, which undoes the operations before the failed one
+        assert_eq!(doc, json!({ "a": 2 }));
+    }
+}
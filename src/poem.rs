@@ -0,0 +1,108 @@
+use std::pin::Pin;
+use std::task::Poll;
+
+use futures::stream::{Stream, TryStream};
+use poem::web::sse::Event;
+use serde::Serialize;
+pub use tokio::sync::mpsc::error::{SendError, TrySendError};
+
+use crate::server::{event_id, sealed, Backend, RetryBackend, SignalEvent, TimerBackend};
+pub use crate::server::{BroadcastSender, LossySender, UnboundedSender};
+use crate::ServerSignalError;
+
+/// Alias for a type-erased error type.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The [`Backend`] of poem, whose events are [`Event`]s.
+///
+/// Poem has no comment event, so heartbeats are sent as events named `heartbeat`.
+#[derive(Clone, Copy, Debug)]
+pub struct Poem;
+
+impl sealed::Sealed for Poem {}
+
+impl Backend for Poem {
+    type Error = BoxError;
+    type Event = Event;
+}
+
+impl RetryBackend for Poem {}
+
+impl TimerBackend for Poem {}
+
+/// A signal owned by the server which writes to the SSE when mutated, as a stream of
+/// [`Event`]s for poem's `SSE` response.
+pub type ServerSentEvents<S> = crate::server::ServerSentEvents<S, Poem>;
+
+/// Sender half of a server-sent events stream, whose custom events are [`Event`]s.
+pub type Sender<T> = crate::server::Sender<T, Poem>;
+
+/// Creates the SSE of each connection of a broadcast channel, see
+/// [`ServerSentEvents::broadcast_channel`].
+pub type BroadcastSubscriber<T> = crate::server::BroadcastSubscriber<T, Poem>;
+
+/// The stream of a [`ServerSentEvents`] created by [`ServerSentEvents::channel_for`].
+pub type ChannelStream<T> = crate::server::ChannelStream<T, Poem>;
+
+/// The stream of a [`ServerSentEvents`] created by [`BroadcastSubscriber::subscribe`].
+pub type BroadcastChannelStream<T> = crate::server::BroadcastChannelStream<T, Poem>;
+
+impl<S> Stream for ServerSentEvents<S>
+where
+    S: TryStream<Error = BoxError>,
+    S::Ok: Serialize,
+{
+    type Item = Event;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        match self.as_mut().poll_signal(cx) {
+            // Poem has no retry field nor comments on a message
+            Poll::Ready(Some(Ok(SignalEvent::Update {
+                id,
+                name,
+                data,
+                meta,
+            }))) => {
+                // Poem splits the data with `str::lines`, which drops a trailing empty line
+                let mut message = data.as_str().to_owned();
+                if message.ends_with('\n') {
+                    message.push('\n');
+                }
+                let event = Event::message(message)
+                    .id(event_id(id, meta.as_ref()))
+                    .event_type(name);
+                Poll::Ready(Some(event))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Error(error)))) => {
+                Poll::Ready(Some(error_event(&error)))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Retry(retry)))) => {
+                let event = Event::retry(retry.as_millis().try_into().unwrap_or(u64::MAX));
+                Poll::Ready(Some(event))
+            }
+            // Poem has no comment event, so this is an event the client does not listen to
+            Poll::Ready(Some(Ok(SignalEvent::Heartbeat))) => {
+                Poll::Ready(Some(Event::message("").event_type("heartbeat")))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Close(name)))) => {
+                Poll::Ready(Some(Event::message(name).event_type("close")))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Custom(event)))) => Poll::Ready(Some(event)),
+            // Poem streams cannot fail, so encoding errors are reported as error events too
+            Poll::Ready(Some(Err(err))) => {
+                let error = ServerSignalError::new(self.name().clone(), err.to_string());
+                Poll::Ready(Some(error_event(&error)))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+fn error_event(error: &ServerSignalError) -> Event {
+    let data = serde_json::to_string(error).expect("server signal errors serialize to json");
+    Event::message(data).event_type("error")
+}
@@ -0,0 +1,90 @@
+//! Resyncs server signals through a leptos server function, see [`sse_snapshot`].
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use leptos::{server, ServerFnError};
+use serde::Serialize;
+
+use crate::ServerSignalUpdate;
+
+/// Encodes the current value of a signal as a snapshot.
+type Snapshot = Arc<dyn Fn() -> Result<ServerSignalUpdate, serde_json::Error> + Send + Sync>;
+
+/// The signals registered with [`register_sse_snapshot`], by name.
+fn snapshots() -> &'static RwLock<HashMap<Cow<'static, str>, Snapshot>> {
+    static SNAPSHOTS: OnceLock<RwLock<HashMap<Cow<'static, str>, Snapshot>>> = OnceLock::new();
+    SNAPSHOTS.get_or_init(Default::default)
+}
+
+/// Registers the signal `name` with [`sse_snapshot`], which responds with the value `current`
+/// returns when a client resyncs the signal.
+///
+/// Register each signal once when the server starts, under the name the client knows it by,
+/// including its namespace. Registering a name again replaces the signal it was registered for.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(all(feature = "ssr", feature = "axum"))]
+/// # {
+/// # use leptos_sse::SignalState;
+/// # #[derive(Clone, Default, serde::Serialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// let count = SignalState::new(Count::default(), 16);
+/// let state = count.clone();
+/// leptos_sse::register_sse_snapshot("counter", move || state.get());
+/// # }
+/// ```
+pub fn register_sse_snapshot<T>(
+    name: impl Into<Cow<'static, str>>,
+    current: impl Fn() -> T + Send + Sync + 'static,
+) where
+    T: Serialize,
+{
+    let name = name.into();
+    let snapshot: Snapshot = {
+        let name = name.clone();
+        Arc::new(move || ServerSignalUpdate::snapshot(name.clone(), &current()))
+    };
+    snapshots()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(name, snapshot);
+}
+
+/// Removes the signal `name` registered with [`register_sse_snapshot`].
+pub fn unregister_sse_snapshot(name: &str) {
+    snapshots()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(name);
+}
+
+/// Returns a snapshot of the current value of the server signal `name`, registered on the
+/// server with [`register_sse_snapshot`].
+///
+/// The client calls this to resync a signal once [`provide_sse_resync_server_fn`] was called,
+/// so no endpoint needs to be wired by hand. It is served at `/api/leptos_sse_snapshot` by the
+/// handler of the server functions of the web framework integration, as any other server
+/// function.
+///
+/// [`provide_sse_resync_server_fn`]: crate::provide_sse_resync_server_fn
+#[server(SseSnapshot, "/api", "Url", "leptos_sse_snapshot")]
+pub async fn sse_snapshot(name: String) -> Result<ServerSignalUpdate, ServerFnError> {
+    // Cloned out, so the lock is not held while the value is encoded
+    let snapshot = snapshots()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(name.as_str())
+        .cloned();
+    match snapshot {
+        Some(snapshot) => snapshot().map_err(|err| ServerFnError::ServerError(err.to_string())),
+        None => Err(ServerFnError::ServerError(format!(
+            "no snapshot was registered for server signal {name}"
+        ))),
+    }
+}
@@ -0,0 +1,104 @@
+use std::pin::Pin;
+use std::task::Poll;
+
+use futures::stream::{Stream, TryStream};
+use rocket::response::stream::Event;
+use serde::Serialize;
+pub use tokio::sync::mpsc::error::{SendError, TrySendError};
+
+use crate::server::{
+    event_id, sealed, Backend, EventMeta, RetryBackend, SignalEvent, TimerBackend,
+};
+pub use crate::server::{BroadcastSender, LossySender, UnboundedSender};
+use crate::ServerSignalError;
+
+/// Alias for a type-erased error type.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The [`Backend`] of rocket, whose events are [`Event`]s.
+#[derive(Clone, Copy, Debug)]
+pub struct Rocket;
+
+impl sealed::Sealed for Rocket {}
+
+impl Backend for Rocket {
+    type Error = BoxError;
+    type Event = Event;
+}
+
+impl RetryBackend for Rocket {}
+
+impl TimerBackend for Rocket {}
+
+/// A signal owned by the server which writes to the SSE when mutated, as a stream of
+/// [`Event`]s for rocket's `EventStream` response.
+pub type ServerSentEvents<S> = crate::server::ServerSentEvents<S, Rocket>;
+
+/// Sender half of a server-sent events stream, whose custom events are [`Event`]s.
+pub type Sender<T> = crate::server::Sender<T, Rocket>;
+
+/// Creates the SSE of each connection of a broadcast channel, see
+/// [`ServerSentEvents::broadcast_channel`].
+pub type BroadcastSubscriber<T> = crate::server::BroadcastSubscriber<T, Rocket>;
+
+/// The stream of a [`ServerSentEvents`] created by [`ServerSentEvents::channel_for`].
+pub type ChannelStream<T> = crate::server::ChannelStream<T, Rocket>;
+
+/// The stream of a [`ServerSentEvents`] created by [`BroadcastSubscriber::subscribe`].
+pub type BroadcastChannelStream<T> = crate::server::BroadcastChannelStream<T, Rocket>;
+
+impl<S> Stream for ServerSentEvents<S>
+where
+    S: TryStream<Error = BoxError>,
+    S::Ok: Serialize,
+{
+    type Item = Event;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        match self.as_mut().poll_signal(cx) {
+            Poll::Ready(Some(Ok(SignalEvent::Update {
+                id,
+                name,
+                data,
+                meta,
+            }))) => {
+                let mut event = Event::data(data.as_str().to_owned())
+                    .id(event_id(id, meta.as_ref()))
+                    .event(name);
+                if let Some(retry) = meta.as_ref().and_then(EventMeta::retry) {
+                    event = event.with_retry(retry);
+                }
+                if let Some(comment) = meta.as_ref().and_then(EventMeta::comment) {
+                    event = event.with_comment(comment.to_owned());
+                }
+                Poll::Ready(Some(event))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Error(error)))) => {
+                Poll::Ready(Some(error_event(&error)))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Retry(retry)))) => {
+                Poll::Ready(Some(Event::retry(retry)))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Heartbeat))) => Poll::Ready(Some(Event::comment(""))),
+            Poll::Ready(Some(Ok(SignalEvent::Close(name)))) => {
+                Poll::Ready(Some(Event::data(name).event("close")))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Custom(event)))) => Poll::Ready(Some(event)),
+            // Rocket event streams cannot fail, so encoding errors are reported as error events too
+            Poll::Ready(Some(Err(err))) => {
+                let error = ServerSignalError::new(self.name().clone(), err.to_string());
+                Poll::Ready(Some(error_event(&error)))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+fn error_event(error: &ServerSignalError) -> Event {
+    let data = serde_json::to_string(error).expect("server signal errors serialize to json");
+    Event::data(data).event("error")
+}
@@ -0,0 +1,2954 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Display};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::task::{Poll, Waker};
+use std::time::Duration;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::future::{self, TryFutureExt};
+use futures::stream::{self, SelectAll, Stream, StreamExt, TryStream, TryStreamExt};
+use leptos::{create_isomorphic_effect, SignalWith};
+use pin_project_lite::pin_project;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc::{
+    self,
+    error::{SendError, TrySendError},
+};
+use tokio::sync::{broadcast, watch};
+use tokio::time::Sleep;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::{BroadcastStream, UnboundedReceiverStream, WatchStream};
+
+use crate::{
+    Codec, CodecError, DiffStrategy, SerializeErrorPolicy, ServerSignalError, ServerSignalUpdate,
+    SseCodec, SseSignal,
+};
+
+/// An event produced by a [`SignalStream`].
+///
+/// The events sent with `Sender::send_event` are type-erased until [`ServerSentEvents`] turns
+/// them back into the event type `E` of its backend.
+pub(crate) enum SignalEvent<E = CustomEvent> {
+    /// An encoded [`ServerSignalUpdate`], sent as an event named after the signal.
+    Update {
+        id: u64,
+        name: Cow<'static, str>,
+        data: EventData,
+        // Set for the values sent with `Sender::send_with_meta`
+        meta: Option<EventMeta>,
+    },
+    /// The source stream failed, sent to the client as an `error` event.
+    Error(ServerSignalError),
+    /// The reconnection time the client should use, sent before any other event.
+    ///
+    /// This is never produced for actix and tide, which have no retry event.
+    Retry(Duration),
+    /// Sent when the stream has been idle for a while, to keep the connection alive.
+    ///
+    /// This is sent as a comment, which the client ignores.
+    Heartbeat,
+    /// Sent as a `close` event once the source stream ends, so the client closes the
+    /// connection rather than reconnecting. The data is the name of the signal.
+    Close(Cow<'static, str>),
+    /// An event of the framework sent with `Sender::send_event`, which is sent as is.
+    Custom(E),
+}
+
+/// The encoded data of an update, sent as the data field of its event.
+///
+/// This is split off the buffer of the [`SignalStream`], which reclaims the memory for the next
+/// update once the event has been written.
+///
+/// It never contains a carriage return, which SSE cannot send. It may span several lines, which
+/// are sent as several data fields, and joined again by the client.
+pub(crate) struct EventData(Bytes);
+
+impl EventData {
+    pub(crate) fn as_str(&self) -> &str {
+        // The codecs only write json, base64 and ascii prefixes
+        std::str::from_utf8(&self.0).unwrap_or_default()
+    }
+}
+
+pin_project! {
+    /// A stream of [`ServerSignalUpdate`]s, diffing each value of `S` against the last one sent.
+    ///
+    /// Errors of `S` do not end the stream, but are reported to the client instead. A value
+    /// which is the same as the last one sent produces no update.
+    ///
+    /// This is shared by the web framework integrations, which turn the updates into events.
+    #[derive(Clone, Debug)]
+    pub(crate) struct SignalStream<S> {
+        name: Cow<'static, str>,
+        #[pin]
+        stream: S,
+        json_value: Value,
+        // The id of the next event, so a reconnecting client can tell which update it saw last
+        next_id: u64,
+        // Whether the next update must be a snapshot, as the value of the client is unknown
+        resync: bool,
+        // Taken once the retry directive has been sent
+        retry: Option<Duration>,
+        // Taken once the current value has been sent as the first update
+        initial_sync: Option<Value>,
+        throttle: Option<Throttle>,
+        heartbeat: Option<Heartbeat>,
+        options: SignalOptions,
+        metrics: Option<MetricsHook>,
+        // Reused to encode each update, so a steady stream of updates does not allocate
+        buffer: BytesMut,
+        // Cleared once the close event has been sent
+        close_on_end: bool,
+        // The metadata of the last value received from a `ChannelReceiver`
+        meta: Option<MetaSlot>,
+        // Set by the stream of `lagging` when values were missed
+        lagged: Option<LagFlag>,
+        // Taken once the token is triggered and the close event has been sent
+        shutdown: Option<Shutdown>,
+        // Set once the stream was shut down, after which it ends
+        shut_down: bool,
+    }
+}
+
+/// What a [`ServerSentEvents`](crate::ServerSentEvents) stream sent for an update, reported to
+/// the callback registered with `with_metrics`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpdateMetrics<'a> {
+    name: &'a str,
+    bytes: usize,
+    operations: usize,
+    snapshot: bool,
+}
+
+impl UpdateMetrics<'_> {
+    /// The name of the signal.
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// The size of the data of the event, once encoded and compressed.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// The number of operations of the json patch, which is zero for a snapshot.
+    pub fn operations(&self) -> usize {
+        self.operations
+    }
+
+    /// Whether the update replaced the whole value of the signal.
+    pub fn snapshot(&self) -> bool {
+        self.snapshot
+    }
+}
+
+/// Several [`ServerSentEvents`](crate::ServerSentEvents) sharing one response, created by
+/// `ServerSentEvents::multiplex`.
+///
+/// The streams are polled in turn, starting after the one which sent the last event, so a burst
+/// of updates of one signal cannot hold back the others. Each stream keeps diffing against the
+/// last value of its own signal, and its events stay named after it. The stream ends once all
+/// of them ended.
+///
+/// The ids of the events count the updates of each signal, so they repeat across the streams,
+/// which cannot be resumed.
+pub struct Multiplex<S> {
+    streams: Vec<Pin<Box<S>>>,
+    next: usize,
+}
+
+impl<S> Multiplex<S> {
+    pub(crate) fn new(streams: impl IntoIterator<Item = S>) -> Self {
+        Multiplex {
+            streams: streams.into_iter().map(Box::pin).collect(),
+            next: 0,
+        }
+    }
+
+    /// Add a stream, for example for a signal shown once the response was created.
+    pub fn push(&mut self, stream: S) {
+        self.streams.push(Box::pin(stream));
+    }
+
+    /// The number of streams which have not ended yet.
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// Whether all streams ended.
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+}
+
+impl<S> Debug for Multiplex<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multiplex")
+            .field("streams", &self.streams.len())
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+impl<S: Stream> Stream for Multiplex<S> {
+    type Item = S::Item;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        for _ in 0..this.streams.len() {
+            if this.next >= this.streams.len() {
+                this.next = 0;
+            }
+            let index = this.next;
+            match this.streams[index].as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    this.next = index + 1;
+                    return Poll::Ready(Some(item));
+                }
+                // The following stream takes its place
+                Poll::Ready(None) => {
+                    this.streams.remove(index);
+                }
+                Poll::Pending => this.next = index + 1,
+            }
+        }
+        if this.streams.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// The callback registered with `with_metrics`.
+type MetricsFn = dyn FnMut(&UpdateMetrics) + Send;
+
+/// Shares the callback registered with `with_metrics` between the clones of a [`SignalStream`].
+#[derive(Clone)]
+struct MetricsHook(Arc<Mutex<MetricsFn>>);
+
+impl Debug for MetricsHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetricsHook").finish_non_exhaustive()
+    }
+}
+
+/// An [`SseCodec`] of the user, shared between the clones of a [`SignalStream`].
+#[derive(Clone)]
+struct CustomCodec(Arc<dyn SseCodec>);
+
+impl Debug for CustomCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomCodec").finish_non_exhaustive()
+    }
+}
+
+/// Coalesces the values of a [`SignalStream`], sending at most one update per `interval`, or
+/// only once no value was received for `interval` when debouncing.
+#[derive(Clone, Debug)]
+struct Throttle {
+    interval: Duration,
+    // Whether the timer restarts with each value, rather than with each update sent
+    debounce: bool,
+    // The latest value received while waiting for the interval to elapse
+    pending: Option<Value>,
+    // Running while updates are held back
+    timer: Option<Timer>,
+    // Whether the source stream ended, in which case the pending value is sent right away
+    done: bool,
+}
+
+/// How many values a throttled [`SignalStream`] takes from its source in a single poll, before
+/// yielding so a source which is always ready cannot hold up the task.
+const MAX_DRAIN: usize = 64;
+
+/// Sends a comment when a [`SignalStream`] has been idle for `interval`.
+#[derive(Clone, Debug)]
+struct Heartbeat {
+    interval: Duration,
+    // Restarted whenever an event is sent
+    timer: Timer,
+}
+
+/// The connection a [`ServerSentEvents`](crate::ServerSentEvents) is created for, given to the
+/// factory of its stream by `ServerSentEvents::for_connection`.
+///
+/// It carries the data the handler extracted from the request, such as the session of the
+/// user, so the stream can be tailored to it without a global map keyed by session.
+#[derive(Clone, Debug)]
+pub struct ConnectionContext<C> {
+    id: u64,
+    data: C,
+}
+
+impl<C> ConnectionContext<C> {
+    pub(crate) fn new(data: C) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        ConnectionContext {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            data,
+        }
+    }
+
+    /// An id unique to the connection among those of this process, for example for logs.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The data extracted from the request.
+    pub fn data(&self) -> &C {
+        &self.data
+    }
+
+    /// Takes the data extracted from the request.
+    pub fn into_data(self) -> C {
+        self.data
+    }
+}
+
+/// Shuts down the [`ServerSentEvents`](crate::ServerSentEvents) streams it was given to,
+/// for example when the server shuts down gracefully.
+///
+/// Once [`ShutdownToken::shutdown`] is called, each stream sends a `close` event, so the client
+/// closes the connection rather than reconnecting to a draining server, and ends. Clones of the
+/// token shut down the same streams, as do the streams given the token after it was triggered.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "axum")]
+/// # fn example(
+/// #     stream: impl futures::stream::TryStream<Ok = i32, Error = axum::BoxError>,
+/// # ) -> Result<(), serde_json::Error> {
+/// # use leptos_sse::axum::ServerSentEvents;
+/// # use leptos_sse::ShutdownToken;
+/// let shutdown = ShutdownToken::new();
+///
+/// // For each connection
+/// let stream = ServerSentEvents::new("counter", stream)?.with_shutdown(&shutdown);
+///
+/// // Once the server is asked to stop
+/// shutdown.shutdown();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ShutdownToken(Arc<watch::Sender<bool>>);
+
+impl ShutdownToken {
+    /// Creates a token which has not been triggered.
+    pub fn new() -> Self {
+        ShutdownToken(Arc::new(watch::Sender::new(false)))
+    }
+
+    /// Shuts down the streams given this token.
+    pub fn shutdown(&self) {
+        self.0.send_replace(true);
+    }
+
+    /// Whether [`ShutdownToken::shutdown`] was called.
+    pub fn is_shutdown(&self) -> bool {
+        *self.0.borrow()
+    }
+}
+
+impl Default for ShutdownToken {
+    fn default() -> Self {
+        ShutdownToken::new()
+    }
+}
+
+/// Waits for a [`ShutdownToken`] to be triggered, for the [`SignalStream`] owning it.
+struct Shutdown {
+    receiver: watch::Receiver<bool>,
+    triggered: Pin<Box<dyn Future<Output = ()> + Send + Sync>>,
+}
+
+impl Shutdown {
+    fn new(receiver: watch::Receiver<bool>) -> Self {
+        let mut waiting = receiver.clone();
+        let triggered = Box::pin(async move {
+            // The token was dropped without being triggered, so it never will be
+            if waiting.wait_for(|shutdown| *shutdown).await.is_err() {
+                future::pending::<()>().await;
+            }
+        });
+        Shutdown {
+            receiver,
+            triggered,
+        }
+    }
+
+    fn poll(&mut self, cx: &mut std::task::Context<'_>) -> Poll<()> {
+        self.triggered.as_mut().poll(cx)
+    }
+}
+
+impl Clone for Shutdown {
+    fn clone(&self) -> Self {
+        Shutdown::new(self.receiver.clone())
+    }
+}
+
+impl Debug for Shutdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shutdown")
+            .field("triggered", &*self.receiver.borrow())
+            .finish()
+    }
+}
+
+/// A [`Sleep`] which can be cloned, like the [`SignalStream`] owning it.
+#[derive(Debug)]
+struct Timer(Pin<Box<Sleep>>);
+
+impl Timer {
+    fn new(duration: Duration) -> Self {
+        Timer(Box::pin(tokio::time::sleep(duration)))
+    }
+
+    fn reset(&mut self, duration: Duration) {
+        self.0
+            .as_mut()
+            .reset(tokio::time::Instant::now() + duration);
+    }
+
+    fn poll(&mut self, cx: &mut std::task::Context<'_>) -> Poll<()> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+impl Clone for Timer {
+    fn clone(&self) -> Self {
+        Timer(Box::pin(tokio::time::sleep_until(self.0.deadline())))
+    }
+}
+
+/// Options configuring how a [`SignalStream`] encodes its events.
+#[derive(Clone, Debug)]
+struct SignalOptions {
+    strategy: DiffStrategy,
+    // Patches larger than this fraction of the value are replaced by a snapshot
+    snapshot_ratio: Option<f64>,
+    // Whether every update is a snapshot, skipping the diff
+    full_value: bool,
+    // Whether integral floats are turned into integers before diffing
+    canonical_numbers: bool,
+    codec: Codec,
+    // Replaces `codec` when set
+    custom_codec: Option<CustomCodec>,
+    #[cfg(feature = "compress")]
+    compression_threshold: usize,
+    // Whether the values are updates built by the caller, which are sent as they are
+    passthrough: bool,
+    serialize_errors: SerializeErrorPolicy,
+    // Set by `with_namespace`, which already prefixed the name with it
+    namespace: Option<Cow<'static, str>>,
+    // Whether the values are base64 encoded bytes, which are sent whole rather than diffed
+    #[cfg(feature = "raw")]
+    raw: bool,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for SignalOptions {
+    fn default() -> Self {
+        SignalOptions {
+            strategy: DiffStrategy::default(),
+            snapshot_ratio: None,
+            full_value: false,
+            canonical_numbers: false,
+            codec: Codec::default(),
+            custom_codec: None,
+            #[cfg(feature = "compress")]
+            compression_threshold: crate::codec::DEFAULT_COMPRESSION_THRESHOLD,
+            passthrough: false,
+            serialize_errors: SerializeErrorPolicy::default(),
+            namespace: None,
+            #[cfg(feature = "raw")]
+            raw: false,
+        }
+    }
+}
+
+impl<S> SignalStream<S> {
+    pub(crate) fn new<T>(
+        name: impl Into<Cow<'static, str>>,
+        stream: S,
+        initial: &T,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        Ok(SignalStream {
+            name: name.into(),
+            stream,
+            json_value: serde_json::to_value(initial)?,
+            next_id: 1,
+            resync: false,
+            retry: None,
+            initial_sync: None,
+            throttle: None,
+            heartbeat: None,
+            options: SignalOptions::default(),
+            metrics: None,
+            buffer: BytesMut::new(),
+            close_on_end: false,
+            meta: None,
+            lagged: None,
+            shutdown: None,
+            shut_down: false,
+        })
+    }
+
+    /// Replaces the source stream with the one `f` wraps it in, keeping everything else.
+    pub(crate) fn map_stream<S2>(self, f: impl FnOnce(S) -> S2) -> SignalStream<S2> {
+        SignalStream {
+            name: self.name,
+            stream: f(self.stream),
+            json_value: self.json_value,
+            next_id: self.next_id,
+            resync: self.resync,
+            retry: self.retry,
+            initial_sync: self.initial_sync,
+            throttle: self.throttle,
+            heartbeat: self.heartbeat,
+            options: self.options,
+            metrics: self.metrics,
+            buffer: self.buffer,
+            close_on_end: self.close_on_end,
+            meta: self.meta,
+            lagged: self.lagged,
+            shutdown: self.shutdown,
+            shut_down: self.shut_down,
+        }
+    }
+
+    /// Creates a stream forwarding the [`ServerSignalUpdate`]s of `stream` as they are.
+    ///
+    /// No value is kept, so the updates are neither diffed nor throttled.
+    pub(crate) fn passthrough(name: impl Into<Cow<'static, str>>, stream: S) -> Self {
+        let mut this = SignalStream {
+            name: name.into(),
+            stream,
+            json_value: Value::Null,
+            next_id: 1,
+            resync: false,
+            retry: None,
+            initial_sync: None,
+            throttle: None,
+            heartbeat: None,
+            options: SignalOptions::default(),
+            metrics: None,
+            buffer: BytesMut::new(),
+            close_on_end: false,
+            meta: None,
+            lagged: None,
+            shutdown: None,
+            shut_down: false,
+        };
+        this.options.passthrough = true;
+        this
+    }
+
+    /// Creates a stream sending the values of `stream`, base64 encoded bytes, as they are.
+    ///
+    /// The values are compared against the last one sent, but not diffed.
+    #[cfg(feature = "raw")]
+    pub(crate) fn raw(name: impl Into<Cow<'static, str>>, stream: S) -> Self {
+        let mut this = SignalStream::passthrough(name, stream);
+        this.options.passthrough = false;
+        this.options.raw = true;
+        this
+    }
+
+    /// Creates a stream of values whose first value is sent as a snapshot, so no initial value
+    /// is needed.
+    pub(crate) fn snapshot_first(name: impl Into<Cow<'static, str>>, stream: S) -> Self {
+        let mut this = SignalStream::passthrough(name, stream);
+        this.options.passthrough = false;
+        this.resync = true;
+        this
+    }
+
+    /// Creates a stream of the values of a broadcast receiver made by [`lagging`].
+    ///
+    /// The first value is sent as a snapshot, so no initial value is needed, and so is the next
+    /// value whenever `lagged` is set.
+    pub(crate) fn broadcast(
+        name: impl Into<Cow<'static, str>>,
+        stream: S,
+        lagged: LagFlag,
+    ) -> Self {
+        let mut this = SignalStream::snapshot_first(name, stream);
+        this.lagged = Some(lagged);
+        this
+    }
+
+    #[cfg(any(feature = "poem", feature = "rocket"))]
+    pub(crate) fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+
+    pub(crate) fn with_retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub(crate) fn with_initial_sync<T>(mut self, current: &T) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        self.initial_sync = Some(serde_json::to_value(current)?);
+        Ok(self)
+    }
+
+    pub(crate) fn resume<T>(
+        mut self,
+        last_event_id: Option<&str>,
+        baseline: impl FnOnce(u64) -> Option<T>,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        let Some(last_event_id) = last_event_id else {
+            return Ok(self);
+        };
+        match last_event_id.trim().parse::<u64>() {
+            Ok(id) => {
+                match baseline(id) {
+                    Some(value) => self.json_value = serde_json::to_value(value)?,
+                    None => self.resync = true,
+                }
+                self.next_id = id.saturating_add(1);
+            }
+            Err(_) => self.resync = true,
+        }
+        Ok(self)
+    }
+
+    // Not offered by tide, which does not run on tokio
+    pub(crate) fn throttle(mut self, interval: Duration) -> Self {
+        self.throttle = Some(Throttle {
+            interval,
+            debounce: false,
+            pending: None,
+            timer: None,
+            done: false,
+        });
+        self
+    }
+
+    // Not offered by tide, which does not run on tokio
+    pub(crate) fn debounce(mut self, interval: Duration) -> Self {
+        self.throttle = Some(Throttle {
+            interval,
+            debounce: true,
+            pending: None,
+            timer: None,
+            done: false,
+        });
+        self
+    }
+
+    // Not offered by tide, which does not run on tokio
+    pub(crate) fn with_heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat = Some(Heartbeat {
+            interval,
+            timer: Timer::new(interval),
+        });
+        self
+    }
+
+    pub(crate) fn with_strategy(mut self, strategy: DiffStrategy) -> Self {
+        self.options.strategy = strategy;
+        self
+    }
+
+    pub(crate) fn with_snapshot_fallback(mut self, ratio: f64) -> Self {
+        self.options.snapshot_ratio = Some(ratio);
+        self
+    }
+
+    pub(crate) fn full_value_mode(mut self) -> Self {
+        self.options.full_value = true;
+        self
+    }
+
+    pub(crate) fn with_number_canonicalization(mut self, canonicalize: bool) -> Self {
+        self.options.canonical_numbers = canonicalize;
+        if canonicalize {
+            canonicalize_numbers(&mut self.json_value);
+            if let Some(current) = &mut self.initial_sync {
+                canonicalize_numbers(current);
+            }
+        }
+        self
+    }
+
+    pub(crate) fn with_codec(mut self, codec: impl SseCodec) -> Self {
+        match codec.builtin() {
+            Some(codec) => {
+                self.options.codec = codec;
+                self.options.custom_codec = None;
+            }
+            None => self.options.custom_codec = Some(CustomCodec(Arc::new(codec))),
+        }
+        self
+    }
+
+    #[cfg(feature = "compress")]
+    pub(crate) fn with_compression_threshold(mut self, bytes: usize) -> Self {
+        self.options.compression_threshold = bytes;
+        self
+    }
+
+    pub(crate) fn on_serialize_error(mut self, policy: SerializeErrorPolicy) -> Self {
+        self.options.serialize_errors = policy;
+        self
+    }
+
+    pub(crate) fn with_namespace(mut self, namespace: impl Into<Cow<'static, str>>) -> Self {
+        let namespace = namespace.into();
+        self.name = crate::namespaced(&namespace, &self.name).into();
+        self.options.namespace = Some(namespace);
+        self
+    }
+
+    pub(crate) fn with_event_meta(mut self, meta: MetaSlot) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    pub(crate) fn with_close_on_end(mut self, close: bool) -> Self {
+        self.close_on_end = close;
+        self
+    }
+
+    pub(crate) fn with_shutdown(mut self, token: &ShutdownToken) -> Self {
+        self.shutdown = Some(Shutdown::new(token.0.subscribe()));
+        self
+    }
+
+    pub(crate) fn with_metrics(
+        mut self,
+        metrics: impl FnMut(&UpdateMetrics) + Send + 'static,
+    ) -> Self {
+        self.metrics = Some(MetricsHook(Arc::new(Mutex::new(metrics))));
+        self
+    }
+}
+
+impl<S> Stream for SignalStream<S>
+where
+    S: TryStream,
+    S::Ok: Serialize,
+    S::Error: From<serde_json::Error> + From<CodecError> + Display,
+{
+    type Item = Result<SignalEvent, S::Error>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().project();
+        if *this.shut_down {
+            return Poll::Ready(None);
+        }
+        if let Some(shutdown) = this.shutdown {
+            if shutdown.poll(cx).is_ready() {
+                *this.shutdown = None;
+                *this.shut_down = true;
+                return Poll::Ready(Some(Ok(SignalEvent::Close(this.name.clone()))));
+            }
+        }
+        // Events received by the channel are sent before anything received after them
+        if let Some(event) = this.meta.as_ref().and_then(MetaSlot::pop_event) {
+            return Poll::Ready(Some(Ok(SignalEvent::Custom(event))));
+        }
+        let mut poll = self.as_mut().poll_event(cx);
+        let this = self.project();
+        // The update is of the last value received, whose metadata is still in the slot
+        if let (Poll::Ready(Some(Ok(SignalEvent::Update { meta, .. }))), Some(slot)) =
+            (&mut poll, &this.meta)
+        {
+            *meta = slot.take_meta();
+        }
+        if poll.is_pending() {
+            if let Some(event) = this.meta.as_ref().and_then(MetaSlot::pop_event) {
+                poll = Poll::Ready(Some(Ok(SignalEvent::Custom(event))));
+            }
+        }
+        if let Poll::Ready(None) = poll {
+            if std::mem::take(this.close_on_end) {
+                poll = Poll::Ready(Some(Ok(SignalEvent::Close(this.name.clone()))));
+            }
+        }
+        let Some(heartbeat) = this.heartbeat else {
+            return poll;
+        };
+        match poll {
+            Poll::Ready(event) => {
+                heartbeat.timer.reset(heartbeat.interval);
+                Poll::Ready(event)
+            }
+            Poll::Pending => {
+                if heartbeat.timer.poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                heartbeat.timer.reset(heartbeat.interval);
+                Poll::Ready(Some(Ok(SignalEvent::Heartbeat)))
+            }
+        }
+    }
+}
+
+impl<S> SignalStream<S>
+where
+    S: TryStream,
+    S::Ok: Serialize,
+    S::Error: From<serde_json::Error> + From<CodecError> + Display,
+{
+    /// Polls the next event of the signal itself, without heartbeats.
+    fn poll_event(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Result<SignalEvent, S::Error>>> {
+        let this = self.project();
+        if let Some(retry) = this.retry.take() {
+            return Poll::Ready(Some(Ok(SignalEvent::Retry(retry))));
+        }
+        let mut state = UpdateState {
+            name: this.name,
+            json_value: this.json_value,
+            next_id: this.next_id,
+            resync: this.resync,
+            lagged: this.lagged,
+            options: this.options,
+            metrics: this.metrics,
+            buffer: this.buffer,
+        };
+        if let Some(current) = this.initial_sync.take() {
+            if let Some(event) = state.next(current)? {
+                return Poll::Ready(Some(Ok(event)));
+            }
+        }
+        let throttle = this
+            .throttle
+            .as_mut()
+            .filter(|_| !state.options.passthrough);
+        let Some(throttle) = throttle else {
+            return match this.stream.try_poll_next(cx) {
+                Poll::Ready(Some(Ok(value))) => {
+                    let new_json = match state.serialize(value)? {
+                        Ok(json) => json,
+                        Err(event) => return Poll::Ready(Some(Ok(event))),
+                    };
+                    match state.next(new_json)? {
+                        Some(event) => Poll::Ready(Some(Ok(event))),
+                        // Nothing changed. Rather than looping over a source which is always
+                        // ready, yield and poll it again on the next wake up.
+                        None => {
+                            cx.waker().wake_by_ref();
+                            Poll::Pending
+                        }
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Ok(state.error(err)))),
+                Poll::Ready(None) => Poll::Ready(None),
+                Poll::Pending => Poll::Pending,
+            };
+        };
+
+        // Drain the source, keeping only the latest value while the interval runs
+        let mut stream = this.stream;
+        let mut drained = 0;
+        while !throttle.done {
+            // Send the pending value once the interval elapsed, even if the source is still ready
+            if throttle
+                .timer
+                .as_mut()
+                .is_some_and(|timer| timer.poll(cx).is_ready())
+            {
+                break;
+            }
+            if drained == MAX_DRAIN {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            drained += 1;
+            match stream.as_mut().try_poll_next(cx) {
+                Poll::Ready(Some(Ok(value))) => {
+                    let new_json = match state.serialize(value)? {
+                        Ok(json) => json,
+                        Err(event) => return Poll::Ready(Some(Ok(event))),
+                    };
+                    if throttle.debounce {
+                        // Wait for the source to be quiet for a whole interval again
+                        match &mut throttle.timer {
+                            Some(timer) => timer.reset(throttle.interval),
+                            None => throttle.timer = Some(Timer::new(throttle.interval)),
+                        }
+                        throttle.pending = Some(new_json);
+                        continue;
+                    }
+                    if throttle.timer.is_some() {
+                        throttle.pending = Some(new_json);
+                        continue;
+                    }
+                    // An unchanged value is not sent, so it does not start an interval either
+                    if let Some(event) = state.update(new_json)? {
+                        throttle.timer = Some(Timer::new(throttle.interval));
+                        return Poll::Ready(Some(Ok(event)));
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Ok(state.error(err)))),
+                Poll::Ready(None) => throttle.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if throttle.done {
+            return match throttle.pending.take() {
+                Some(new_json) => Poll::Ready(state.update(new_json)?.map(Ok)),
+                None => Poll::Ready(None),
+            };
+        }
+        if let Some(timer) = &mut throttle.timer {
+            if timer.poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            throttle.timer = None;
+            if let Some(new_json) = throttle.pending.take() {
+                // Send the final value of the interval, and hold back updates for another one
+                if let Some(event) = state.update(new_json)? {
+                    if !throttle.debounce {
+                        throttle.timer = Some(Timer::new(throttle.interval));
+                    }
+                    return Poll::Ready(Some(Ok(event)));
+                }
+            }
+        }
+        Poll::Pending
+    }
+}
+
+/// The fields of a [`SignalStream`] needed to turn a value into an event.
+struct UpdateState<'a> {
+    name: &'a Cow<'static, str>,
+    json_value: &'a mut Value,
+    next_id: &'a mut u64,
+    resync: &'a mut bool,
+    lagged: &'a Option<LagFlag>,
+    options: &'a SignalOptions,
+    metrics: &'a Option<MetricsHook>,
+    buffer: &'a mut BytesMut,
+}
+
+impl UpdateState<'_> {
+    /// Turns a value of the source stream into an event.
+    fn next(&mut self, json: Value) -> Result<Option<SignalEvent>, CodecError> {
+        if self.options.passthrough {
+            self.forward(json)
+        } else {
+            self.update(json)
+        }
+    }
+
+    /// Diffs `new_json` against the last value sent, and encodes the update.
+    ///
+    /// Returns `None` if `new_json` is the same as the last value sent.
+    fn update(&mut self, new_json: Value) -> Result<Option<SignalEvent>, CodecError> {
+        #[cfg(feature = "raw")]
+        if self.options.raw {
+            return self.update_raw(new_json);
+        }
+        // Values were missed, so the client may not have the value the patch is diffed against
+        if let Some(lagged) = self.lagged {
+            if lagged.swap(false, Ordering::Relaxed) {
+                *self.resync = true;
+            }
+        }
+        let mut update = if std::mem::take(self.resync) {
+            ServerSignalUpdate::snapshot(self.name.clone(), &new_json)?
+        } else if self.options.full_value {
+            if new_json == *self.json_value {
+                return Ok(None);
+            }
+            ServerSignalUpdate::snapshot(self.name.clone(), &new_json)?
+        } else {
+            self.options
+                .strategy
+                .diff(self.name.clone(), self.json_value, &new_json)?
+        };
+        if update.snapshot.is_none() && update.patch.0.is_empty() {
+            return Ok(None);
+        }
+        if let Some(ratio) = self
+            .options
+            .snapshot_ratio
+            .filter(|_| update.snapshot.is_none())
+        {
+            let patch_len = serde_json::to_string(&update.patch)?.len();
+            let value_len = serde_json::to_string(&new_json)?.len();
+            if patch_len as f64 > value_len as f64 * ratio {
+                update = ServerSignalUpdate::snapshot(update.name, &new_json)?;
+            }
+        }
+        *self.json_value = new_json;
+        self.encode(update).map(Some)
+    }
+
+    /// Sends a raw value, unless it is the same as the last value sent.
+    #[cfg(feature = "raw")]
+    fn update_raw(&mut self, new_json: Value) -> Result<Option<SignalEvent>, CodecError> {
+        if new_json == *self.json_value {
+            return Ok(None);
+        }
+        let Value::String(encoded) = &new_json else {
+            return Err(CodecError::new("raw values must be base64 encoded strings"));
+        };
+        let data = EventData(Bytes::copy_from_slice(encoded.as_bytes()));
+        *self.json_value = new_json;
+        let id = *self.next_id;
+        *self.next_id += 1;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(signal = %self.name, id, bytes = data.0.len(), "sending raw server signal value");
+        if let Some(MetricsHook(metrics)) = self.metrics {
+            let mut metrics = metrics.lock().unwrap_or_else(PoisonError::into_inner);
+            metrics(&UpdateMetrics {
+                name: self.name,
+                bytes: data.0.len(),
+                operations: 0,
+                snapshot: true,
+            });
+        }
+        Ok(Some(SignalEvent::Update {
+            id,
+            name: self.name.clone(),
+            data,
+            meta: None,
+        }))
+    }
+
+    /// Encodes an update built by the caller, which must be for this signal.
+    fn forward(&mut self, json: Value) -> Result<Option<SignalEvent>, CodecError> {
+        let mut update: ServerSignalUpdate = serde_json::from_value(json)?;
+        // Updates may be built with the name of the signal before it was namespaced
+        if let Some(namespace) = &self.options.namespace {
+            let name = crate::namespaced(namespace, &update.name);
+            if name == *self.name {
+                update.name = name.into();
+            }
+        }
+        if update.name != *self.name {
+            return Ok(Some(self.error(format!(
+                "update for {} sent on the stream of {}",
+                update.name, self.name
+            ))));
+        }
+        self.encode(update).map(Some)
+    }
+
+    fn encode(&mut self, mut update: ServerSignalUpdate) -> Result<SignalEvent, CodecError> {
+        let id = *self.next_id;
+        *self.next_id += 1;
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("server_signal_update", signal = %update.name, id).entered();
+        // The id doubles as the version, so a resumed stream carries on where it left off
+        update.version = id;
+        let buffer = &mut *self.buffer;
+        #[allow(unused_mut)]
+        let mut compressed = false;
+        match &self.options.custom_codec {
+            Some(CustomCodec(codec)) => buffer.extend_from_slice(codec.encode(&update)?.as_bytes()),
+            None => {
+                self.options.codec.encode_into(&update, buffer.writer())?;
+                #[cfg(feature = "compress")]
+                if buffer.len() > self.options.compression_threshold {
+                    let encoded = buffer.split();
+                    crate::codec::compress(&encoded, buffer.writer())?;
+                    compressed = true;
+                }
+            }
+        }
+        let data = EventData(buffer.split().freeze());
+        if data.0.contains(&b'\r') {
+            return Err(CodecError::new(
+                "the encoded update contains a carriage return, which cannot be sent over SSE",
+            ));
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            operations = update.patch.0.len(),
+            snapshot = update.snapshot.is_some(),
+            bytes = data.0.len(),
+            "sending server signal update"
+        );
+        // The whole update as the client receives it, before it is compressed
+        #[cfg(feature = "debug-logging")]
+        tracing::debug!(
+            update = %serde_json::to_string(&update).unwrap_or_default(),
+            "server signal update payload"
+        );
+        if let Some(MetricsHook(metrics)) = self.metrics {
+            let mut metrics = metrics.lock().unwrap_or_else(PoisonError::into_inner);
+            metrics(&UpdateMetrics {
+                name: &update.name,
+                bytes: data.0.len(),
+                operations: update.patch.0.len(),
+                snapshot: update.snapshot.is_some(),
+            });
+        }
+        // Compressed updates are sent as events of their own, so each is marked as such
+        let name = if compressed {
+            crate::codec::compressed_event_type(&update.name).into()
+        } else {
+            update.name
+        };
+        Ok(SignalEvent::Update {
+            id,
+            name,
+            data,
+            meta: None,
+        })
+    }
+
+    /// Serializes a value of the source stream, or returns the error event sent in its place
+    /// if the value is skipped.
+    fn serialize(
+        &self,
+        value: impl Serialize,
+    ) -> Result<Result<Value, SignalEvent>, serde_json::Error> {
+        match serde_json::to_value(value) {
+            Ok(mut json) => {
+                if self.options.canonical_numbers && !self.options.passthrough {
+                    canonicalize_numbers(&mut json);
+                }
+                Ok(Ok(json))
+            }
+            Err(err) if self.options.serialize_errors == SerializeErrorPolicy::Skip => {
+                Ok(Err(self.error(err)))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn error(&self, err: impl Display) -> SignalEvent {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(signal = %self.name, error = %err, "sending server signal error");
+        SignalEvent::Error(ServerSignalError::new(self.name.clone(), err.to_string()))
+    }
+}
+
+impl DiffStrategy {
+    /// Creates the update of the signal `name` from `old` to `new`.
+    pub(crate) fn diff(
+        self,
+        name: impl Into<Cow<'static, str>>,
+        old: &Value,
+        new: &Value,
+    ) -> Result<ServerSignalUpdate, serde_json::Error> {
+        let name = name.into();
+        if self == DiffStrategy::Snapshot {
+            return ServerSignalUpdate::snapshot(name, new);
+        }
+        // A patch between values of different shapes replaces nearly everything anyway, and is
+        // brittle to apply, so the value is replaced as a whole
+        if shape_changed(old, new) {
+            return ServerSignalUpdate::snapshot(name, new);
+        }
+        let update = ServerSignalUpdate::new_from_json::<Value>(name, old, new);
+        let use_snapshot = match self {
+            DiffStrategy::Bounded { max_operations } => update.patch.0.len() > max_operations,
+            DiffStrategy::Smallest => {
+                serde_json::to_string(&update.patch)?.len() > serde_json::to_string(new)?.len()
+            }
+            _ => false,
+        };
+        if use_snapshot {
+            ServerSignalUpdate::snapshot(update.name, new)
+        } else {
+            Ok(update)
+        }
+    }
+}
+
+/// Whether the root of `new` has another shape than `old`, such as an enum switching variants.
+///
+/// Serde serializes unit variants of externally tagged enums to strings, and the other variants
+/// to an object with the name of the variant as its only key, so a variant switch either changes
+/// the json type of the root or its only key.
+fn shape_changed(old: &Value, new: &Value) -> bool {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) if old.len() == 1 && new.len() == 1 => {
+            old.keys().next() != new.keys().next()
+        }
+        (Value::Object(_), Value::Object(_))
+        | (Value::Array(_), Value::Array(_))
+        | (Value::String(_), Value::String(_))
+        | (Value::Number(_), Value::Number(_))
+        | (Value::Bool(_), Value::Bool(_))
+        | (Value::Null, Value::Null) => false,
+        _ => true,
+    }
+}
+
+/// Turns the floats of `value` which hold an integer into integers, so `1.0` and `1` are the
+/// same once diffed.
+///
+/// Floats outside the range of `i64` and `u64` are kept, as are those with a fraction. Every
+/// integral float within that range converts exactly.
+fn canonicalize_numbers(value: &mut Value) {
+    match value {
+        Value::Number(number) => {
+            let Some(float) = number.as_f64().filter(|_| number.is_f64()) else {
+                return;
+            };
+            if float.fract() != 0.0 {
+                return;
+            }
+            // `i64::MAX as f64` and `u64::MAX as f64` round up to the first float past them
+            if float >= i64::MIN as f64 && float < i64::MAX as f64 {
+                *number = (float as i64).into();
+            } else if float >= 0.0 && float < u64::MAX as f64 {
+                *number = (float as u64).into();
+            }
+        }
+        Value::Array(array) => array.iter_mut().for_each(canonicalize_numbers),
+        Value::Object(object) => object.values_mut().for_each(canonicalize_numbers),
+        _ => {}
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "compress", any(feature = "actix", feature = "axum")))] {
+        pin_project! {
+            /// Gzips the body of a SSE response, flushing the compressed data of each event
+            /// right away.
+            ///
+            /// Compression middleware usually buffers the body until enough data was written,
+            /// which holds back events. Each chunk of `S` is a whole event, so it is followed by
+            /// a sync flush, which costs a few bytes but lets the client decompress and dispatch
+            /// it on arrival.
+            pub(crate) struct GzipStream<S> {
+                #[pin]
+                stream: S,
+                // Taken once the stream ended and the gzip trailer was sent
+                encoder: Option<flate2::write::GzEncoder<Vec<u8>>>,
+            }
+        }
+
+        impl<S> GzipStream<S> {
+            pub(crate) fn new(stream: S) -> Self {
+                GzipStream {
+                    stream,
+                    encoder: Some(flate2::write::GzEncoder::new(
+                        Vec::new(),
+                        flate2::Compression::default(),
+                    )),
+                }
+            }
+        }
+
+        impl<S> Stream for GzipStream<S>
+        where
+            S: TryStream<Ok = Bytes>,
+            S::Error: From<std::io::Error>,
+        {
+            type Item = Result<Bytes, S::Error>;
+
+            fn poll_next(
+                self: Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> Poll<Option<Self::Item>> {
+                use std::io::Write;
+
+                let mut this = self.project();
+                let Some(encoder) = this.encoder else {
+                    return Poll::Ready(None);
+                };
+                loop {
+                    match futures::ready!(this.stream.as_mut().try_poll_next(cx)) {
+                        Some(Ok(chunk)) => {
+                            encoder.write_all(&chunk)?;
+                            // A sync flush, so the client can decompress everything sent so far
+                            encoder.flush()?;
+                            let compressed = std::mem::take(encoder.get_mut());
+                            if !compressed.is_empty() {
+                                return Poll::Ready(Some(Ok(Bytes::from(compressed))));
+                            }
+                        }
+                        Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                        None => {
+                            let trailer = this.encoder.take().map(|encoder| encoder.finish());
+                            return match trailer {
+                                Some(trailer) => Poll::Ready(Some(Ok(Bytes::from(trailer?)))),
+                                None => Poll::Ready(None),
+                            };
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The response headers a SSE response needs to reach the client unbuffered.
+///
+/// Proxies such as nginx buffer responses by default, which holds back events until the buffer
+/// fills up. `X-Accel-Buffering: no` turns this off for a single response, and `Cache-Control:
+/// no-cache` keeps caches from storing the stream. Some of the responders of the web frameworks
+/// set the content type and cache control themselves, but none turns off buffering.
+///
+/// The pairs convert into the headers of most frameworks, for example with axum:
+///
+/// ```no_run
+/// # #[cfg(feature = "axum")]
+/// # mod app {
+/// # use std::time::Duration;
+/// # use axum::response::IntoResponse;
+/// # use leptos_sse::axum::ServerSentEvents;
+/// # #[derive(Default, serde::Serialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// async fn handle_sse() -> impl IntoResponse {
+///     let (_, stream) = ServerSentEvents::<()>::channel::<Count>("counter", 1).unwrap();
+///     (leptos_sse::sse_headers(), stream.into_responder(Duration::from_secs(15)))
+/// }
+/// # }
+/// ```
+///
+/// or with actix:
+///
+/// ```no_run
+/// # #[cfg(feature = "actix")]
+/// # fn example(stream: leptos_sse::actix::ServerSentEvents<leptos_sse::actix::ChannelStream<i32>>) {
+/// # use std::time::Duration;
+/// # use actix_web::Responder;
+/// let mut response = stream.into_responder(Duration::from_secs(15)).customize();
+/// for header in leptos_sse::sse_headers() {
+///     response = response.insert_header(header);
+/// }
+/// # }
+/// ```
+pub fn sse_headers() -> [(&'static str, &'static str); 3] {
+    [
+        ("content-type", "text/event-stream"),
+        ("cache-control", "no-cache"),
+        ("x-accel-buffering", "no"),
+    ]
+}
+
+/// The fields of the event of a value sent with `Sender::send_with_meta`.
+///
+/// Not every framework can set every field, see the documentation of each. Newlines, which
+/// would end the field, are replaced by spaces.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventMeta {
+    id: Option<String>,
+    retry: Option<Duration>,
+    comment: Option<String>,
+}
+
+impl EventMeta {
+    /// Creates metadata which leaves every field as it would be otherwise.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send the event with `id`, rather than the version of the update.
+    ///
+    /// The browser sends the id of the last event it received when it reconnects, so a stream
+    /// resumed from it must understand this id too.
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(single_line(id.into()));
+        self
+    }
+
+    /// Set the reconnection time of the client along with the event.
+    ///
+    /// This is not sent by actix, poem and tide.
+    pub fn with_retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Send `comment` along with the event, which the client ignores, for example to trace
+    /// where an update came from.
+    ///
+    /// This is not sent by actix, poem and tide.
+    pub fn with_comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(single_line(comment.into()));
+        self
+    }
+
+    /// The id set by [`EventMeta::with_id`].
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// The reconnection time set by [`EventMeta::with_retry`].
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// The comment set by [`EventMeta::with_comment`].
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+}
+
+/// The id of the event of the update `id`, unless its metadata sets another one.
+pub(crate) fn event_id(id: u64, meta: Option<&EventMeta>) -> String {
+    match meta.and_then(EventMeta::id) {
+        Some(id) => id.to_owned(),
+        None => id.to_string(),
+    }
+}
+
+/// Replaces the line breaks of `field`, which cannot be sent in a single SSE field.
+fn single_line(field: String) -> String {
+    if field.contains(['\n', '\r']) {
+        field.replace(['\n', '\r'], " ")
+    } else {
+        field
+    }
+}
+
+/// Passes on the values of `stream` for which `predicate` returns `true`, given the last value
+/// passed on and the new one. The first value is always passed on.
+///
+/// The values it drops never reach the [`SignalStream`], so they are not diffed either.
+pub(crate) fn filter_values<S, F>(
+    stream: S,
+    predicate: F,
+) -> impl TryStream<Ok = S::Ok, Error = S::Error>
+where
+    S: TryStream,
+    S::Ok: Clone,
+    F: Fn(&S::Ok, &S::Ok) -> bool,
+{
+    let mut previous = None;
+    stream.into_stream().try_filter(move |value| {
+        let send = previous
+            .as_ref()
+            .is_none_or(|previous| predicate(previous, value));
+        if send {
+            previous = Some(value.clone());
+        }
+        future::ready(send)
+    })
+}
+
+/// Turns each value of `stream` into a `T` with the async `transform`, one at a time so the
+/// values keep their order, converting the errors of both into `B`.
+pub(crate) fn try_then<S, F, Fut, T, E, B>(
+    stream: S,
+    mut transform: F,
+) -> impl TryStream<Ok = T, Error = B>
+where
+    S: TryStream,
+    S::Error: Into<B>,
+    F: FnMut(S::Ok) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Into<B>,
+{
+    stream
+        .into_stream()
+        .map_err(Into::into)
+        .and_then(move |value| transform(value).map_err(Into::into))
+}
+
+/// A message sent through the channel of a `Sender`.
+pub(crate) enum Message<T> {
+    /// A value, with the metadata of its event.
+    Value(T, Option<EventMeta>),
+    /// An event of the framework, sent as is with `Sender::send_event`.
+    Event(CustomEvent),
+}
+
+/// An event of the framework, sent by a [`SignalStream`] between its updates.
+///
+/// It is only created by [`Sender::send_event`], which takes the event type of its backend,
+/// and received by the [`ServerSentEvents`] of the same backend.
+pub(crate) struct CustomEvent(Box<dyn Any + Send>);
+
+impl CustomEvent {
+    pub(crate) fn new<E: Send + 'static>(event: E) -> Self {
+        CustomEvent(Box::new(event))
+    }
+
+    /// Returns the event, which is an `E` as the sender and the stream share their backend.
+    pub(crate) fn into_event<E: 'static>(self) -> E {
+        *self
+            .0
+            .downcast()
+            .expect("events are sent with the event type of the backend")
+    }
+}
+
+impl fmt::Debug for CustomEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomEvent").finish_non_exhaustive()
+    }
+}
+
+/// Holds the metadata of the last value a [`ChannelReceiver`] received, until the
+/// [`SignalStream`] sends its update, along with the events it received since.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MetaSlot {
+    meta: Arc<Mutex<Option<EventMeta>>>,
+    events: Arc<Mutex<VecDeque<CustomEvent>>>,
+}
+
+impl MetaSlot {
+    fn take_meta(&self) -> Option<EventMeta> {
+        self.meta
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    fn pop_event(&self) -> Option<CustomEvent> {
+        self.events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front()
+    }
+}
+
+/// Creates the channel of a `Sender`, holding up to `buffer` values.
+pub(crate) fn meta_channel<T>(buffer: usize) -> (mpsc::Sender<Message<T>>, ChannelReceiver<T>) {
+    let (sender, receiver) = mpsc::channel(buffer);
+    let receiver = ChannelReceiver {
+        receiver,
+        meta: MetaSlot::default(),
+    };
+    (sender, receiver)
+}
+
+/// Returns the value of a message which could not be sent.
+pub(crate) fn unsent<T>(err: SendError<Message<T>>) -> SendError<T> {
+    match err.0 {
+        Message::Value(value, _) => SendError(value),
+        Message::Event(_) => unreachable!("an event was sent as a value"),
+    }
+}
+
+/// Returns the event of a message which could not be sent.
+pub(crate) fn unsent_event<T, E: 'static>(err: SendError<Message<T>>) -> SendError<E> {
+    match err.0 {
+        Message::Event(event) => SendError(event.into_event()),
+        Message::Value(..) => unreachable!("a value was sent as an event"),
+    }
+}
+
+/// Returns the value of a message which could not be sent right away.
+pub(crate) fn try_unsent<T>(err: TrySendError<Message<T>>) -> TrySendError<T> {
+    match err {
+        TrySendError::Full(Message::Value(value, _)) => TrySendError::Full(value),
+        TrySendError::Closed(Message::Value(value, _)) => TrySendError::Closed(value),
+        _ => unreachable!("an event was sent as a value"),
+    }
+}
+
+/// The receiver half of the channel of a `Sender`, see `ChannelStream`.
+///
+/// The metadata of each value is kept for the `ServerSentEvents` to send along with its update,
+/// and the events are queued for it to send in between.
+#[derive(Debug)]
+pub struct ChannelReceiver<T> {
+    receiver: mpsc::Receiver<Message<T>>,
+    meta: MetaSlot,
+}
+
+impl<T> ChannelReceiver<T> {
+    pub(crate) fn meta(&self) -> MetaSlot {
+        self.meta.clone()
+    }
+}
+
+impl<T> Stream for ChannelReceiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Option<T>> {
+        match self.receiver.poll_recv(cx) {
+            Poll::Ready(Some(Message::Value(value, meta))) => {
+                *self
+                    .meta
+                    .meta
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = meta;
+                Poll::Ready(Some(value))
+            }
+            Poll::Ready(Some(Message::Event(event))) => {
+                // Sent by the `SignalStream` once it is polled again
+                self.meta
+                    .events
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push_back(event);
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Set by the stream of [`lagging`] when the receiver lagged behind.
+pub(crate) type LagFlag = Arc<AtomicBool>;
+
+/// Turns `receiver` into a stream of its values, which skips the values missed when the
+/// receiver lagged behind, but sets the returned flag instead.
+pub(crate) fn lagging<T>(receiver: broadcast::Receiver<T>) -> (LagFlag, impl Stream<Item = T>)
+where
+    T: Clone + Send + 'static,
+{
+    let lagged = LagFlag::default();
+    let flag = lagged.clone();
+    let stream = BroadcastStream::new(receiver).filter_map(move |value| {
+        let value = match value {
+            Ok(value) => Some(value),
+            Err(BroadcastStreamRecvError::Lagged(_)) => {
+                flag.store(true, Ordering::Relaxed);
+                None
+            }
+        };
+        future::ready(value)
+    });
+    (lagged, stream)
+}
+
+/// The current value of a signal shared by every connection, which broadcasts its changes.
+///
+/// The producer changes the value with [`SignalState::set`] or [`SignalState::update`], and
+/// each connection creates its SSE with `ServerSentEvents::subscribe`, which starts from the
+/// current value and receives the changes made after it. Both happen under a lock, so a new
+/// connection neither misses a change nor receives one already part of its first value.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "axum")]
+/// # fn example() -> Result<(), serde_json::Error> {
+/// # use leptos_sse::axum::ServerSentEvents;
+/// # use leptos_sse::SignalState;
+/// # #[derive(Clone, serde::Serialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// let state = SignalState::new(Count { value: 0 }, 16);
+///
+/// // For each connection
+/// let stream = ServerSentEvents::<()>::subscribe(&state, "counter")?;
+///
+/// // In the producer
+/// state.update(|count| count.value += 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SignalState<T> {
+    value: Arc<RwLock<T>>,
+    sender: broadcast::Sender<T>,
+}
+
+impl<T> SignalState<T>
+where
+    T: Clone,
+{
+    /// Creates the state of a signal starting at `value`.
+    ///
+    /// The `capacity` argument controls how many changes are kept for connections which are
+    /// slow to receive them. A connection which falls further behind misses changes, and is sent
+    /// a snapshot of the next value instead.
+    pub fn new(value: T, capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        SignalState {
+            value: Arc::new(RwLock::new(value)),
+            sender,
+        }
+    }
+
+    /// Replaces the value, and sends it to every connection.
+    pub fn set(&self, value: T) {
+        self.update(|current| *current = value);
+    }
+
+    /// Changes the value with `f`, and sends the new value to every connection.
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        let mut value = self.value.write().unwrap_or_else(PoisonError::into_inner);
+        f(&mut value);
+        // Sent under the lock, so it is ordered with the subscriptions. Sending fails when no
+        // connection is subscribed, which is fine.
+        let _ = self.sender.send(value.clone());
+    }
+
+    /// The current value.
+    pub fn get(&self) -> T {
+        self.value
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// The number of connections subscribed to the changes.
+    pub fn receiver_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// The current value, and a receiver of the changes made after it.
+    pub(crate) fn subscribe(&self) -> (T, broadcast::Receiver<T>) {
+        let value = self.value.read().unwrap_or_else(PoisonError::into_inner);
+        (value.clone(), self.sender.subscribe())
+    }
+}
+
+/// Turns the values of a reactive `source` into a stream, through an effect owned by the
+/// current reactive owner.
+///
+/// The stream yields the current value first, then the value after each change, skipping those
+/// replaced before they were polled. It ends once the owner is disposed. Once the stream is
+/// dropped, the effect stops tracking `source`, so it no longer runs.
+pub(crate) fn signal_values<T>(
+    source: impl SignalWith<Value = T> + 'static,
+) -> impl Stream<Item = T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    let (sender, receiver) = watch::channel(None);
+    create_isomorphic_effect(move |_| {
+        if !sender.is_closed() {
+            let value = source.with(T::clone);
+            sender.send_replace(Some(value));
+        }
+    });
+    WatchStream::new(receiver).filter_map(future::ready)
+}
+
+/// The state shared by both halves of a lossy channel.
+#[derive(Debug)]
+struct LossyState<T> {
+    queue: VecDeque<T>,
+    capacity: usize,
+    waker: Option<Waker>,
+    senders: usize,
+    receiving: bool,
+}
+
+/// Creates a channel holding at most `capacity` values, where sending to a full channel drops
+/// the oldest value rather than waiting.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub(crate) fn lossy_channel<T>(capacity: usize) -> (LossySender<T>, LossyReceiver<T>) {
+    assert!(
+        capacity > 0,
+        "lossy channel capacity must be greater than zero"
+    );
+    let state = Arc::new(Mutex::new(LossyState {
+        queue: VecDeque::with_capacity(capacity),
+        capacity,
+        waker: None,
+        senders: 1,
+        receiving: true,
+    }));
+    (LossySender(state.clone()), LossyReceiver(state))
+}
+
+/// Sender half of a lossy server-sent events stream, see [`ServerSentEvents::channel_lossy`].
+#[derive(Debug)]
+pub struct LossySender<T>(Arc<Mutex<LossyState<T>>>);
+
+impl<T> LossySender<T> {
+    /// Send an SSE message without waiting, returning the oldest unsent message if it had to be
+    /// dropped to make room.
+    ///
+    /// This fails if the [`ServerSentEvents`] has been dropped, in which case the value is
+    /// returned.
+    pub fn send(&self, value: T) -> Result<Option<T>, SendError<T>>
+    where
+        T: Serialize,
+    {
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if !state.receiving {
+            return Err(SendError(value));
+        }
+        let dropped = if state.queue.len() == state.capacity {
+            state.queue.pop_front()
+        } else {
+            None
+        };
+        state.queue.push_back(value);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        Ok(dropped)
+    }
+
+    /// Whether the [`ServerSentEvents`] has been dropped, in which case sending fails.
+    pub fn is_closed(&self) -> bool {
+        !self
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .receiving
+    }
+}
+
+impl<T> Clone for LossySender<T> {
+    fn clone(&self) -> Self {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .senders += 1;
+        LossySender(self.0.clone())
+    }
+}
+
+impl<T> Drop for LossySender<T> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        state.senders -= 1;
+        // Lets the receiver end once it drained the queue
+        if state.senders == 0 {
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Receiver half of a channel created by [`lossy_channel`], which ends once every sender has
+/// been dropped and the queued values have been received.
+#[derive(Debug)]
+pub(crate) struct LossyReceiver<T>(Arc<Mutex<LossyState<T>>>);
+
+impl<T> Stream for LossyReceiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Option<T>> {
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(value) = state.queue.pop_front() {
+            return Poll::Ready(Some(value));
+        }
+        if state.senders == 0 {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for LossyReceiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        state.receiving = false;
+        state.queue.clear();
+    }
+}
+
+pub(crate) mod sealed {
+    /// Keeps [`Backend`](super::Backend) implemented by the web framework integrations only.
+    pub trait Sealed {}
+}
+
+/// A web framework integration, which the events of a [`ServerSentEvents`] are written with.
+///
+/// Each integration has a module of its own, such as `leptos_sse::axum`, with a marker type
+/// implementing this trait, and aliases of [`ServerSentEvents`], [`Sender`] and
+/// [`BroadcastSubscriber`] for it. The stream and the responder of a [`ServerSentEvents`]
+/// depend on the integration, while its constructors and senders are shared.
+pub trait Backend: sealed::Sealed {
+    /// The error of the streams of values.
+    type Error: From<BroadcastStreamRecvError>;
+    /// An event of the web framework, sent as is with [`Sender::send_event`].
+    type Event: Send + 'static;
+}
+
+/// A [`Backend`] whose events can set the time the client waits before reconnecting, see
+/// [`ServerSentEvents::with_retry`].
+pub trait RetryBackend: Backend {}
+
+/// A [`Backend`] polling the streams on tokio, whose timers throttle the updates and send
+/// heartbeats, see [`ServerSentEvents::throttle`].
+pub trait TimerBackend: Backend {}
+
+/// The stream of a [`ServerSentEvents`] created by [`ServerSentEvents::channel_for`].
+pub type ChannelStream<T, B> =
+    futures::stream::Map<ChannelReceiver<T>, fn(T) -> Result<T, <B as Backend>::Error>>;
+
+/// The stream of a [`ServerSentEvents`] created by [`BroadcastSubscriber::subscribe`].
+pub type BroadcastChannelStream<T, B> = futures::stream::Map<
+    BroadcastStream<T>,
+    fn(Result<T, BroadcastStreamRecvError>) -> Result<T, <B as Backend>::Error>,
+>;
+
+/// The next event of a [`ServerSentEvents`], in the event type of its backend.
+type PollSignal<E, Err> = Poll<Option<Result<SignalEvent<E>, Err>>>;
+
+/// The sender and the [`ServerSentEvents`] of a channel, or the error encoding its initial value.
+type ChannelResult<Tx, S, B> = Result<(Tx, ServerSentEvents<S, B>), serde_json::Error>;
+
+pin_project! {
+    /// A signal owned by the server which writes to the SSE when mutated.
+    ///
+    /// Updates are sent as events named after the signal. Errors of the stream are sent to the
+    /// client as an `error` event rather than ending the SSE, see
+    /// [`sse_last_error`](crate::sse_last_error). A value which is the same as the last one sent
+    /// produces no event.
+    ///
+    /// The events are written with the web framework `B`, whose module aliases this type, such
+    /// as `leptos_sse::axum::ServerSentEvents`.
+    #[derive(Clone, Debug)]
+    pub struct ServerSentEvents<S, B> {
+        #[pin]
+        inner: SignalStream<S>,
+        backend: PhantomData<fn() -> B>,
+    }
+}
+
+impl<S, B> ServerSentEvents<S, B> {
+    fn from_inner(inner: SignalStream<S>) -> Self {
+        ServerSentEvents {
+            inner,
+            backend: PhantomData,
+        }
+    }
+
+    /// The name of the signal, including its namespace.
+    ///
+    /// Only used by the backends whose streams cannot fail, to report their errors.
+    #[cfg(any(feature = "poem", feature = "rocket"))]
+    pub(crate) fn name(&self) -> &Cow<'static, str> {
+        self.inner.name()
+    }
+}
+
+impl<S, B> ServerSentEvents<S, B>
+where
+    B: Backend,
+    S: TryStream,
+    S::Ok: Serialize,
+    S::Error: From<serde_json::Error> + From<CodecError> + Display,
+{
+    /// Polls the next event, for the backend to write with its own event type.
+    pub(crate) fn poll_signal(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> PollSignal<B::Event, S::Error> {
+        self.project().inner.poll_next(cx).map(|event| {
+            event.map(|event| {
+                event.map(|event| match event {
+                    SignalEvent::Update {
+                        id,
+                        name,
+                        data,
+                        meta,
+                    } => SignalEvent::Update {
+                        id,
+                        name,
+                        data,
+                        meta,
+                    },
+                    SignalEvent::Error(error) => SignalEvent::Error(error),
+                    SignalEvent::Retry(retry) => SignalEvent::Retry(retry),
+                    SignalEvent::Heartbeat => SignalEvent::Heartbeat,
+                    SignalEvent::Close(name) => SignalEvent::Close(name),
+                    SignalEvent::Custom(event) => SignalEvent::Custom(event.into_event()),
+                })
+            })
+        })
+    }
+}
+
+impl<S, B: RetryBackend> ServerSentEvents<S, B> {
+    /// Set the time the client waits before reconnecting when the connection is lost.
+    ///
+    /// This is sent to the client once, before any update.
+    pub fn with_retry(mut self, retry: Duration) -> Self {
+        self.inner = self.inner.with_retry(retry);
+        self
+    }
+}
+
+impl<S, B: TimerBackend> ServerSentEvents<S, B> {
+    /// Send at most one update per `interval`, coalescing the values received in between.
+    ///
+    /// The first value is sent right away. Values received while the interval runs are not
+    /// dropped, but replaced by the latest one, which is diffed against the last value sent once
+    /// the interval elapses. The client always ends up with the final value of the stream.
+    pub fn throttle(mut self, interval: Duration) -> Self {
+        self.inner = self.inner.throttle(interval);
+        self
+    }
+
+    /// Send a single update once no value was received for `interval`, skipping the values of
+    /// a burst.
+    ///
+    /// Each value restarts the interval, and only the latest one is kept, which is diffed against
+    /// the last value sent. This suits values whose intermediate states do not matter, such as
+    /// the position of a slider being dragged. It replaces [`ServerSentEvents::throttle`].
+    pub fn debounce(mut self, interval: Duration) -> Self {
+        self.inner = self.inner.debounce(interval);
+        self
+    }
+
+    /// Send a heartbeat when no update was sent for `interval`, to keep the connection alive.
+    ///
+    /// This keeps proxies from closing an idle connection, regardless of the keep alive of the
+    /// web framework. The heartbeat is a comment, or an event named `heartbeat` with poem, which
+    /// has no comment event. The client ignores both.
+    pub fn with_heartbeat(mut self, interval: Duration) -> Self {
+        self.inner = self.inner.with_heartbeat(interval);
+        self
+    }
+}
+
+impl<S, B: Backend> ServerSentEvents<S, B> {
+    /// Create a new [`ServerSentEvents`] a stream, initializing `T` to default.
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn new<T>(name: impl Into<Cow<'static, str>>, stream: S) -> Result<Self, serde_json::Error>
+    where
+        T: Default + Serialize,
+        S: TryStream<Ok = T, Error = B::Error>,
+    {
+        Self::new_with_initial(name, stream, &T::default())
+    }
+
+    /// Create a new [`ServerSentEvents`] for a connection, with the stream built by `factory`
+    /// from `data` extracted from the request, initializing `T` to default.
+    ///
+    /// This threads the data of the request, such as the session of the user, into the
+    /// producer of the stream, along with an id for the connection, see [`ConnectionContext`].
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn for_connection<C, T>(
+        name: impl Into<Cow<'static, str>>,
+        data: C,
+        factory: impl FnOnce(ConnectionContext<C>) -> S,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Default + Serialize,
+        S: TryStream<Ok = T, Error = B::Error>,
+    {
+        Self::new(name, factory(ConnectionContext::new(data)))
+    }
+
+    /// Create a new [`ServerSentEvents`] from a stream whose values are turned into `T` by the
+    /// async `transform`, initializing `T` to default.
+    ///
+    /// This suits values which need an async lookup, such as a database query, before they
+    /// can be sent. The values are transformed one at a time, so the updates keep the order of
+    /// the stream, and each `T` is diffed against the last one sent as for any stream. The
+    /// errors of both the stream and `transform` are reported to the client.
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn from_try_stream_async<T, E, F, Fut>(
+        name: impl Into<Cow<'static, str>>,
+        stream: S,
+        transform: F,
+    ) -> Result<ServerSentEvents<impl TryStream<Ok = T, Error = B::Error>, B>, serde_json::Error>
+    where
+        T: Default + Serialize,
+        S: TryStream,
+        S::Error: Into<B::Error>,
+        F: FnMut(S::Ok) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Into<B::Error>,
+    {
+        ServerSentEvents::new(name, try_then(stream, transform))
+    }
+
+    /// Create a new [`ServerSentEvents`] a stream, initializing `T` to default and diffing its
+    /// values with `strategy`.
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn new_with_strategy<T>(
+        name: impl Into<Cow<'static, str>>,
+        stream: S,
+        strategy: DiffStrategy,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Default + Serialize,
+        S: TryStream<Ok = T, Error = B::Error>,
+    {
+        let mut this = Self::new(name, stream)?;
+        this.inner = this.inner.with_strategy(strategy);
+        Ok(this)
+    }
+
+    /// Create a new [`ServerSentEvents`] syncing the part of each value of `stream` returned
+    /// by `projection`, initializing `T` to default.
+    ///
+    /// Only the projected json is diffed and sent, so fields left out of it are never sent to
+    /// the client, whose signal can be a smaller type deserializing from the projection. The
+    /// projection must only depend on the value, or it produces spurious updates.
+    ///
+    /// This function can fail if serilization of the projection of the default `T` fails.
+    pub fn new_projected<T>(
+        name: impl Into<Cow<'static, str>>,
+        stream: S,
+        mut projection: impl FnMut(&T) -> serde_json::Value,
+    ) -> Result<
+        ServerSentEvents<impl TryStream<Ok = serde_json::Value, Error = B::Error>, B>,
+        serde_json::Error,
+    >
+    where
+        T: Default,
+        S: TryStream<Ok = T, Error = B::Error>,
+    {
+        let initial = projection(&T::default());
+        let stream = stream.map_ok(move |value| projection(&value));
+        ServerSentEvents::new_with_initial(name, stream, &initial)
+    }
+
+    /// Create a new [`ServerSentEvents`] a stream, initializing `T` to default and encoding its
+    /// values with `codec`.
+    ///
+    /// The client must decode them with the same codec, see [`SseCodec`].
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn new_with_codec<T>(
+        name: impl Into<Cow<'static, str>>,
+        stream: S,
+        codec: impl SseCodec,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Default + Serialize,
+        S: TryStream<Ok = T, Error = B::Error>,
+    {
+        let mut this = Self::new(name, stream)?;
+        this.inner = this.inner.with_codec(codec);
+        Ok(this)
+    }
+
+    /// Create a new [`ServerSentEvents`] a stream, named after `T` and initializing it to default.
+    ///
+    /// The client should create the signal with [`create_sse_signal_for`](crate::create_sse_signal_for).
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn for_signal<T>(stream: S) -> Result<Self, serde_json::Error>
+    where
+        T: SseSignal + Default + Serialize,
+        S: TryStream<Ok = T, Error = B::Error>,
+    {
+        Self::new(T::NAME, stream)
+    }
+
+    /// Create a new [`ServerSentEvents`] a stream, initializing `T` to `initial`.
+    ///
+    /// The client must start from the same value, for example by using
+    /// [`create_sse_signal_with_initial`](crate::create_sse_signal_with_initial),
+    /// otherwise the first patch will not apply.
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn new_with_initial<T>(
+        name: impl Into<Cow<'static, str>>,
+        stream: S,
+        initial: &T,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+        S: TryStream<Ok = T, Error = B::Error>,
+    {
+        Ok(ServerSentEvents::from_inner(SignalStream::new(
+            name, stream, initial,
+        )?))
+    }
+
+    /// Send `current` as the first update, rather than waiting for the stream to produce a value.
+    ///
+    /// A client which just connected shows the current value right away, instead of its initial
+    /// value until the stream changes. The update is diffed against the initial value like any
+    /// other, so the client converges as long as it starts from the same one.
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn with_initial_sync<T>(mut self, current: &T) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        self.inner = self.inner.with_initial_sync(current)?;
+        Ok(self)
+    }
+
+    /// Resume the SSE of a client that reconnected, given the `Last-Event-ID` request header.
+    ///
+    /// Each update is sent with an increasing id, which the browser sends back in the
+    /// `Last-Event-ID` header when it reconnects. `baseline` is called with that id, and should
+    /// return the value of `T` that was sent with it, so the next update is diffed against the
+    /// value the client already has. If it returns `None`, the next update is sent as a snapshot
+    /// instead. Without a `Last-Event-ID` header, this does nothing.
+    ///
+    /// The ids count the updates of one signal, so this cannot be combined with
+    /// [`multiplex`](Self::multiplex), whose signals send the same ids.
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn resume<T>(
+        mut self,
+        last_event_id: Option<&str>,
+        baseline: impl FnOnce(u64) -> Option<T>,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        self.inner = self.inner.resume(last_event_id, baseline)?;
+        Ok(self)
+    }
+
+    /// Only send the values for which `predicate` returns `true`, given the last value sent
+    /// and the new one.
+    ///
+    /// A value it rejects is dropped as if it was never received: the client keeps the last
+    /// value sent, and the next value is diffed against it. This suits changes which only
+    /// matter past a threshold, such as a position which moved by more than a few pixels. The
+    /// first value is always sent.
+    #[doc(alias = "filter_map_values")]
+    pub fn filter_values<T>(
+        self,
+        predicate: impl Fn(&T, &T) -> bool,
+    ) -> ServerSentEvents<impl TryStream<Ok = T, Error = B::Error>, B>
+    where
+        S: TryStream<Ok = T, Error = B::Error>,
+        T: Clone,
+    {
+        ServerSentEvents::from_inner(
+            self.inner
+                .map_stream(|stream| filter_values(stream, predicate)),
+        )
+    }
+
+    /// Send a snapshot of the value instead of a patch larger than `ratio` times the value,
+    /// once both are serialized to json.
+    ///
+    /// A patch nearly as large as the value saves little, and replacing the value is cheaper
+    /// for the client to apply. With a `ratio` of `1.0`, this is like [`DiffStrategy::Smallest`],
+    /// but it also applies on top of the other strategies.
+    pub fn with_snapshot_fallback(mut self, ratio: f64) -> Self {
+        self.inner = self.inner.with_snapshot_fallback(ratio);
+        self
+    }
+
+    /// Send every update as a snapshot of the whole value, without diffing it against the last
+    /// value sent.
+    ///
+    /// This trades bandwidth for the cost of the diff, which pays off for small values changing
+    /// on every update, such as a counter, whose patch is about as large as the value anyway.
+    /// The client replaces its value rather than patching it. Unlike [`DiffStrategy::Snapshot`],
+    /// values equal to the last value sent are skipped, which only takes a comparison.
+    pub fn full_value_mode(mut self) -> Self {
+        self.inner = self.inner.full_value_mode();
+        self
+    }
+
+    /// Turn the floats holding an integer, such as `1.0`, into integers before diffing, when
+    /// `canonicalize` is `true`.
+    ///
+    /// A value computed as a float may be the same number as the last one sent, but serialized
+    /// differently, which would send a patch changing nothing. Floats with a fraction, and
+    /// those outside the range of 64 bit integers, are sent as they are. The client
+    /// deserializes integers into float fields too.
+    pub fn with_number_canonicalization(mut self, canonicalize: bool) -> Self {
+        self.inner = self.inner.with_number_canonicalization(canonicalize);
+        self
+    }
+
+    /// Encode updates with `codec`, which defaults to [`Codec::default`](crate::Codec::default).
+    ///
+    /// This is either a built-in [`Codec`](crate::Codec) or a custom [`SseCodec`]. The client
+    /// must be able to decode it, see [`SseOptions::with_codec`](crate::SseOptions::with_codec).
+    pub fn with_codec(mut self, codec: impl SseCodec) -> Self {
+        self.inner = self.inner.with_codec(codec);
+        self
+    }
+
+    /// Deflate updates larger than `bytes` once encoded, which defaults to 1 KiB.
+    ///
+    /// Smaller updates are sent as is, as compressing them would not save much.
+    #[cfg(feature = "compress")]
+    pub fn with_compression_threshold(mut self, bytes: usize) -> Self {
+        self.inner = self.inner.with_compression_threshold(bytes);
+        self
+    }
+
+    /// Set what happens to values which fail to serialize.
+    ///
+    /// By default the stream ends with the error. With [`SerializeErrorPolicy::Skip`], the
+    /// value is skipped instead, so a single bad value does not end a long-lived connection.
+    pub fn on_serialize_error(mut self, policy: SerializeErrorPolicy) -> Self {
+        self.inner = self.inner.on_serialize_error(policy);
+        self
+    }
+
+    /// Prefix the name of the signal with `namespace`, so `counter` is sent as
+    /// `namespace:counter`.
+    ///
+    /// This keeps signals of independent components apart when they share a SSE. The client
+    /// must create the signal under the same namespace, see
+    /// [`provide_sse_namespace`](crate::provide_sse_namespace).
+    pub fn with_namespace(mut self, namespace: impl Into<Cow<'static, str>>) -> Self {
+        self.inner = self.inner.with_namespace(namespace);
+        self
+    }
+
+    /// Send a `close` event once the stream ends, so the client closes the connection.
+    ///
+    /// Otherwise the browser reconnects as if the connection dropped, which for a finite stream
+    /// starts it over again. The signals of the client keep their last value.
+    pub fn with_close_on_end(mut self, close: bool) -> Self {
+        self.inner = self.inner.with_close_on_end(close);
+        self
+    }
+
+    /// End the stream once `token` is triggered, after sending a `close` event so the client
+    /// does not reconnect, see [`ShutdownToken`].
+    pub fn with_shutdown(mut self, token: &ShutdownToken) -> Self {
+        self.inner = self.inner.with_shutdown(token);
+        self
+    }
+
+    /// Call `metrics` with the name, size and number of patch operations of each update sent.
+    ///
+    /// This is not called for errors or heartbeats. Clones of the stream share the callback.
+    pub fn with_metrics(mut self, metrics: impl FnMut(&UpdateMetrics) + Send + 'static) -> Self {
+        self.inner = self.inner.with_metrics(metrics);
+        self
+    }
+
+    fn with_event_meta(mut self, meta: MetaSlot) -> Self {
+        self.inner = self.inner.with_event_meta(meta);
+        self
+    }
+
+    /// Send the events of several signals through one response, taking turns so a burst of
+    /// updates of one signal cannot hold back the others.
+    ///
+    /// Each signal keeps its own name and is diffed against its own last value. The streams
+    /// must have the same type, which boxing their source streams provides.
+    ///
+    /// The id of each event is the version of its signal, so the signals send the same ids, and
+    /// the `Last-Event-ID` of a reconnecting client cannot tell them apart. Send a snapshot of
+    /// each signal instead of using [`resume`](Self::resume).
+    pub fn multiplex(streams: impl IntoIterator<Item = Self>) -> Multiplex<Self> {
+        Multiplex::new(streams)
+    }
+
+    /// Create a server-sent-events (SSE) channel pair.
+    ///
+    /// The `buffer` argument controls how many unsent messages can be stored without waiting.
+    ///
+    /// The first item in the tuple is the MPSC channel sender half.
+    pub fn channel<T>(
+        name: impl Into<Cow<'static, str>>,
+        buffer: usize,
+    ) -> ChannelResult<Sender<T, B>, impl TryStream<Ok = T, Error = B::Error>, B>
+    where
+        T: Default + Serialize,
+    {
+        let (sender, receiver) = meta_channel::<T>(buffer);
+        let meta = receiver.meta();
+        let stream = receiver.map(Ok);
+        Ok((
+            Sender::new(sender),
+            ServerSentEvents::new(name, stream)?.with_event_meta(meta),
+        ))
+    }
+
+    /// Create a server-sent-events (SSE) channel pair, initializing `T` to `initial`.
+    ///
+    /// This is like [`ServerSentEvents::channel`], for types without a meaningful default. The
+    /// client must start from the same value, see [`ServerSentEvents::new_with_initial`].
+    pub fn channel_with_initial<T>(
+        name: impl Into<Cow<'static, str>>,
+        buffer: usize,
+        initial: &T,
+    ) -> ChannelResult<Sender<T, B>, impl TryStream<Ok = T, Error = B::Error>, B>
+    where
+        T: Serialize,
+    {
+        let (sender, receiver) = meta_channel::<T>(buffer);
+        let meta = receiver.meta();
+        let stream = receiver.map(Ok);
+        Ok((
+            Sender::new(sender),
+            ServerSentEvents::new_with_initial(name, stream, initial)?.with_event_meta(meta),
+        ))
+    }
+
+    /// Create a server-sent-events (SSE) channel pair, where sending never waits.
+    ///
+    /// At most `capacity` unsent messages are stored. Sending while it is full drops the oldest
+    /// one, so the client skips intermediate values under backpressure but still ends up with
+    /// the newest value, as each value is diffed against the last one sent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn channel_lossy<T>(
+        name: impl Into<Cow<'static, str>>,
+        capacity: usize,
+    ) -> ChannelResult<LossySender<T>, impl TryStream<Ok = T, Error = B::Error>, B>
+    where
+        T: Default + Serialize,
+    {
+        let (sender, receiver) = lossy_channel::<T>(capacity);
+        let stream = receiver.map(Ok);
+        Ok((sender, ServerSentEvents::new(name, stream)?))
+    }
+
+    /// Create a server-sent-events (SSE) channel pair, where sending never waits nor drops
+    /// messages.
+    ///
+    /// The unsent messages are stored without limit, so a client which receives them slower
+    /// than they are sent grows the memory of the server until it catches up, or forever. Only
+    /// use it when the messages are bursty but bounded overall, otherwise prefer
+    /// [`ServerSentEvents::channel`], which makes the sender wait, or
+    /// [`ServerSentEvents::channel_lossy`], which drops the oldest messages.
+    pub fn unbounded_channel<T>(
+        name: impl Into<Cow<'static, str>>,
+    ) -> ChannelResult<UnboundedSender<T>, impl TryStream<Ok = T, Error = B::Error>, B>
+    where
+        T: Default + Serialize,
+    {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let stream = UnboundedReceiverStream::new(receiver).map(Ok);
+        Ok((
+            UnboundedSender(sender),
+            ServerSentEvents::new(name, stream)?,
+        ))
+    }
+
+    /// Create a new [`ServerSentEvents`] from a stream of updates built by the caller.
+    ///
+    /// The updates are sent as they are rather than diffed, which lets the caller compute them
+    /// more cheaply than by diffing the whole value. The name of each update must be `name`,
+    /// otherwise it is reported to the client as an error. As no value is kept, the updates are
+    /// not throttled, and a client resuming with [`ServerSentEvents::resume`] is not sent a
+    /// snapshot.
+    pub fn from_updates(name: impl Into<Cow<'static, str>>, stream: S) -> Self
+    where
+        S: TryStream<Ok = ServerSignalUpdate, Error = B::Error>,
+    {
+        ServerSentEvents::from_inner(SignalStream::passthrough(name, stream))
+    }
+
+    /// Create a new [`ServerSentEvents`] sending each value of `stream` whole, as the bytes
+    /// returned by `encoder`.
+    ///
+    /// The values are neither serialized to json nor diffed, which suits values such as images
+    /// for which a json patch is meaningless. The bytes are base64 encoded, and decoded by the
+    /// signal created with [`create_sse_signal_raw`](crate::create_sse_signal_raw) on the client.
+    /// A value encoded to the same bytes as the last one sent produces no event.
+    #[cfg(feature = "raw")]
+    pub fn raw<T>(
+        name: impl Into<Cow<'static, str>>,
+        stream: S,
+        mut encoder: impl FnMut(&T) -> Vec<u8>,
+    ) -> ServerSentEvents<impl TryStream<Ok = String, Error = B::Error>, B>
+    where
+        S: TryStream<Ok = T, Error = B::Error>,
+    {
+        let stream = stream.map_ok(move |value| crate::codec::encode_raw(&encoder(&value)));
+        ServerSentEvents::from_inner(SignalStream::raw(name, stream))
+    }
+
+    /// Create a server-sent-events (SSE) channel pair sending updates built by the caller.
+    ///
+    /// See [`ServerSentEvents::from_updates`] and [`Sender::send_patch`].
+    pub fn patch_channel(
+        name: impl Into<Cow<'static, str>>,
+        buffer: usize,
+    ) -> (
+        Sender<ServerSignalUpdate, B>,
+        ServerSentEvents<impl TryStream<Ok = ServerSignalUpdate, Error = B::Error>, B>,
+    ) {
+        let (sender, receiver) = meta_channel::<ServerSignalUpdate>(buffer);
+        let meta = receiver.meta();
+        let stream = receiver.map(Ok);
+        (
+            Sender::new(sender),
+            ServerSentEvents::from_updates(name, stream).with_event_meta(meta),
+        )
+    }
+
+    /// Create a new [`ServerSentEvents`] sending the values received by a broadcast `receiver`.
+    ///
+    /// The first value is sent as a snapshot, which replaces the value of the client, so `T`
+    /// needs no default and the client may start from any value. If the receiver lags behind
+    /// and misses values, the next value is sent as a snapshot too, rather than as a patch
+    /// against a value the client may not have.
+    ///
+    /// To share a sender between connections, see [`ServerSentEvents::broadcast_channel`].
+    pub fn from_broadcast<T>(
+        name: impl Into<Cow<'static, str>>,
+        receiver: broadcast::Receiver<T>,
+    ) -> ServerSentEvents<impl TryStream<Ok = T, Error = B::Error>, B>
+    where
+        T: Clone + Send + Serialize + 'static,
+    {
+        let (lagged, stream) = lagging(receiver);
+        ServerSentEvents::from_inner(SignalStream::broadcast(name, stream.map(Ok), lagged))
+    }
+
+    /// Create the SSE of a new connection to a [`SignalState`] shared by every connection.
+    ///
+    /// The current value of `state` is sent as a snapshot first, so `T` needs no default and
+    /// the client may start from any value, followed by the changes made after it. A connection
+    /// which lags behind is sent a snapshot of the next value, as for
+    /// [`ServerSentEvents::from_broadcast`].
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn subscribe<T>(
+        state: &SignalState<T>,
+        name: impl Into<Cow<'static, str>>,
+    ) -> Result<ServerSentEvents<impl TryStream<Ok = T, Error = B::Error>, B>, serde_json::Error>
+    where
+        T: Clone + Send + Serialize + 'static,
+    {
+        let (current, receiver) = state.subscribe();
+        let (lagged, stream) = lagging(receiver);
+        Ok(ServerSentEvents::from_inner(
+            SignalStream::broadcast(name, stream.map(Ok), lagged).with_initial_sync(&current)?,
+        ))
+    }
+
+    /// Create a new [`ServerSentEvents`] sending the values of a reactive `source`, such as a
+    /// [`ReadSignal`](leptos::ReadSignal) or [`Memo`](leptos::Memo) of the server.
+    ///
+    /// The value is read by an effect owned by the current reactive owner, so the SSE follows
+    /// `source` for as long as that owner lives, and ends once it is disposed. Create it in the
+    /// owner the connection is handled in, and update `source` within the same reactive runtime.
+    /// The first value is sent as a snapshot, so `T` needs no default, and a value replaced
+    /// before it was sent is skipped.
+    pub fn from_signal<T>(
+        name: impl Into<Cow<'static, str>>,
+        source: impl SignalWith<Value = T> + 'static,
+    ) -> ServerSentEvents<impl TryStream<Ok = T, Error = B::Error>, B>
+    where
+        T: Clone + Send + Sync + Serialize + 'static,
+    {
+        ServerSentEvents::from_inner(SignalStream::snapshot_first(
+            name,
+            signal_values(source).map(Ok),
+        ))
+    }
+
+    /// Create a new [`ServerSentEvents`] sending the values received by a broadcast `receiver`
+    /// for which `predicate` returns `true`.
+    ///
+    /// This is like [`ServerSentEvents::from_broadcast`], for values meant for some clients
+    /// only, such as the members of a room. The values which are filtered out are never sent,
+    /// and the next update is diffed against the last value this client received.
+    pub fn filtered_broadcast<T>(
+        name: impl Into<Cow<'static, str>>,
+        receiver: broadcast::Receiver<T>,
+        mut predicate: impl FnMut(&T) -> bool + 'static,
+    ) -> ServerSentEvents<impl TryStream<Ok = T, Error = B::Error>, B>
+    where
+        T: Clone + Send + Serialize + 'static,
+    {
+        let (lagged, stream) = lagging(receiver);
+        let stream = stream.filter(move |value| std::future::ready(predicate(value)));
+        ServerSentEvents::from_inner(SignalStream::broadcast(name, stream.map(Ok), lagged))
+    }
+
+    /// Create a broadcast channel, sending each value to the SSE of every connected client.
+    ///
+    /// The `capacity` argument controls how many values are kept for clients which are slow to
+    /// receive them. A client which falls further behind misses values, which is reported to it
+    /// as an error, but the next update it receives is still diffed against the value it has.
+    ///
+    /// The first item in the tuple is the sender half, and the second creates the SSE of each
+    /// connection with [`BroadcastSubscriber::subscribe`].
+    pub fn broadcast_channel<T>(
+        name: impl Into<Cow<'static, str>>,
+        capacity: usize,
+    ) -> (BroadcastSender<T>, BroadcastSubscriber<T, B>)
+    where
+        T: Clone + Send + 'static,
+    {
+        let (sender, _) = broadcast::channel(capacity);
+        let subscriber = BroadcastSubscriber {
+            name: name.into(),
+            sender: sender.clone(),
+            backend: PhantomData,
+        };
+        (BroadcastSender(sender), subscriber)
+    }
+}
+
+impl<T, B: Backend> ServerSentEvents<ChannelStream<T, B>, B> {
+    /// Create a server-sent-events (SSE) channel pair, named after `T`.
+    ///
+    /// This is like [`ServerSentEvents::channel`], but the stream type can be named, which the
+    /// helpers generated by `#[derive(ServerSignal)]` rely on.
+    pub fn channel_for(buffer: usize) -> ChannelResult<Sender<T, B>, ChannelStream<T, B>, B>
+    where
+        T: SseSignal + Default + Serialize,
+    {
+        let (sender, receiver) = meta_channel::<T>(buffer);
+        let meta = receiver.meta();
+        let stream = receiver.map(Ok as fn(T) -> Result<T, B::Error>);
+        Ok((
+            Sender::new(sender),
+            ServerSentEvents::new(T::NAME, stream)?.with_event_meta(meta),
+        ))
+    }
+}
+
+impl<S, B: Backend> ServerSentEvents<SelectAll<S>, B> {
+    /// Create a new [`ServerSentEvents`] from several streams of the same signal, initializing
+    /// `T` to default.
+    ///
+    /// The values of all streams are diffed against the last value sent, whichever stream it
+    /// came from, so the client stays consistent as the sources interleave. The streams must be
+    /// [`Unpin`], which [`Box::pin`] provides. The stream ends once all of them ended.
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn from_merged<T>(
+        name: impl Into<Cow<'static, str>>,
+        streams: impl IntoIterator<Item = S>,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Default + Serialize,
+        S: Stream<Item = Result<T, B::Error>> + Unpin,
+    {
+        ServerSentEvents::new(name, stream::select_all(streams))
+    }
+}
+
+/// Sender half of a server-sent events stream.
+#[derive(Clone, Debug)]
+pub struct Sender<T, B> {
+    sender: mpsc::Sender<Message<T>>,
+    backend: PhantomData<fn() -> B>,
+}
+
+impl<T, B> Sender<T, B> {
+    fn new(sender: mpsc::Sender<Message<T>>) -> Self {
+        Sender {
+            sender,
+            backend: PhantomData,
+        }
+    }
+
+    /// Send an SSE message.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>>
+    where
+        T: Serialize,
+    {
+        self.sender
+            .send(Message::Value(value, None))
+            .await
+            .map_err(unsent)
+    }
+
+    /// Send an SSE message, setting the fields of its event with `meta`.
+    ///
+    /// Values sent while the stream is throttled are coalesced, keeping the metadata of the
+    /// last one. A value which produces no event, as it is the same as the last one sent,
+    /// sends no metadata either.
+    pub async fn send_with_meta(&self, value: T, meta: EventMeta) -> Result<(), SendError<T>>
+    where
+        T: Serialize,
+    {
+        self.sender
+            .send(Message::Value(value, Some(meta)))
+            .await
+            .map_err(unsent)
+    }
+
+    /// Attempts to immediately send an SSE message.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>>
+    where
+        T: Serialize,
+    {
+        self.sender
+            .try_send(Message::Value(value, None))
+            .map_err(try_unsent)
+    }
+
+    /// Send an event of the web framework as is, bypassing the diffing of the values.
+    ///
+    /// The client can listen for a named event with its own event listener. It is sent after
+    /// the updates of the values sent before it, though a throttled stream may send the update
+    /// of a coalesced value after it.
+    pub async fn send_event(&self, event: B::Event) -> Result<(), SendError<B::Event>>
+    where
+        B: Backend,
+    {
+        self.sender
+            .send(Message::Event(CustomEvent::new(event)))
+            .await
+            .map_err(unsent_event)
+    }
+
+    /// Completes once the [`ServerSentEvents`] has been dropped, for example because the client
+    /// disconnected.
+    ///
+    /// A producer can select on this to stop computing values no one receives.
+    pub async fn closed(&self) {
+        self.sender.closed().await
+    }
+
+    /// Whether the [`ServerSentEvents`] has been dropped, in which case sending fails.
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+impl<B> Sender<ServerSignalUpdate, B> {
+    /// Send an update built by the caller, see [`ServerSentEvents::patch_channel`].
+    pub async fn send_patch(
+        &self,
+        update: ServerSignalUpdate,
+    ) -> Result<(), SendError<ServerSignalUpdate>> {
+        self.sender
+            .send(Message::Value(update, None))
+            .await
+            .map_err(unsent)
+    }
+
+    /// Attempts to immediately send an update built by the caller.
+    pub fn try_send_patch(
+        &self,
+        update: ServerSignalUpdate,
+    ) -> Result<(), TrySendError<ServerSignalUpdate>> {
+        self.sender
+            .try_send(Message::Value(update, None))
+            .map_err(try_unsent)
+    }
+}
+
+/// Sender half of an unbounded server-sent events stream, see
+/// [`ServerSentEvents::unbounded_channel`].
+#[derive(Clone, Debug)]
+pub struct UnboundedSender<T>(mpsc::UnboundedSender<T>);
+
+impl<T> UnboundedSender<T> {
+    /// Send an SSE message without waiting.
+    ///
+    /// This fails if the [`ServerSentEvents`] has been dropped, in which case the value is
+    /// returned.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>>
+    where
+        T: Serialize,
+    {
+        self.0.send(value)
+    }
+
+    /// Completes once the [`ServerSentEvents`] has been dropped, for example because the client
+    /// disconnected.
+    pub async fn closed(&self) {
+        self.0.closed().await
+    }
+
+    /// Whether the [`ServerSentEvents`] has been dropped, in which case sending fails.
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+}
+
+/// Sender half of a broadcast channel, see [`ServerSentEvents::broadcast_channel`].
+#[derive(Clone, Debug)]
+pub struct BroadcastSender<T>(broadcast::Sender<T>);
+
+impl<T> BroadcastSender<T> {
+    /// Send an SSE message to every connected client, returning how many received it.
+    ///
+    /// This fails if no client is connected, in which case the value is returned.
+    pub fn send(&self, value: T) -> Result<usize, broadcast::error::SendError<T>>
+    where
+        T: Serialize,
+    {
+        self.0.send(value)
+    }
+
+    /// The number of connected clients.
+    pub fn receiver_count(&self) -> usize {
+        self.0.receiver_count()
+    }
+}
+
+/// Creates the SSE of each connection of a broadcast channel, see
+/// [`ServerSentEvents::broadcast_channel`].
+///
+/// The streams end once the sender and every subscriber have been dropped.
+#[derive(Clone, Debug)]
+pub struct BroadcastSubscriber<T, B> {
+    name: Cow<'static, str>,
+    sender: broadcast::Sender<T>,
+    backend: PhantomData<fn() -> B>,
+}
+
+impl<T, B> BroadcastSubscriber<T, B>
+where
+    T: Clone + Send + Serialize + 'static,
+    B: Backend,
+{
+    /// Create the SSE of a new connection, receiving the values sent from now on.
+    ///
+    /// The client starts from the default value, and is sent `current` as the first update,
+    /// so it shows the value other clients already have rather than waiting for the next one.
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn subscribe(
+        &self,
+        current: &T,
+    ) -> Result<ServerSentEvents<BroadcastChannelStream<T, B>, B>, serde_json::Error>
+    where
+        T: Default,
+    {
+        self.subscribe_with_initial(&T::default(), current)
+    }
+
+    /// Create the SSE of a new connection, for a client starting from `initial`.
+    ///
+    /// This is like [`BroadcastSubscriber::subscribe`], for types without a meaningful default.
+    /// The client must start from the same value, see
+    /// [`create_sse_signal_with_initial`](crate::create_sse_signal_with_initial).
+    ///
+    /// This function can fail if serilization of `T` fails.
+    pub fn subscribe_with_initial(
+        &self,
+        initial: &T,
+        current: &T,
+    ) -> Result<ServerSentEvents<BroadcastChannelStream<T, B>, B>, serde_json::Error> {
+        let stream = BroadcastStream::new(self.sender.subscribe()).map(
+            (|value| value.map_err(Into::into))
+                as fn(Result<T, BroadcastStreamRecvError>) -> Result<T, B::Error>,
+        );
+        ServerSentEvents::new_with_initial(self.name.clone(), stream, initial)?
+            .with_initial_sync(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn canonical(mut value: Value) -> Value {
+        canonicalize_numbers(&mut value);
+        value
+    }
+
+    #[test]
+    fn integral_floats_become_integers() {
+        assert_eq!(canonical(json!(1.0)), json!(1));
+        assert_eq!(
+            canonical(json!([-3.0, { "a": 2.0 }])),
+            json!([-3, { "a": 2 }])
+        );
+        assert_eq!(canonical(json!(1.5)), json!(1.5));
+        // Negative zero is the same as zero once canonical
+        assert_eq!(canonical(json!(-0.0)), json!(0));
+        let update = ServerSignalUpdate::new_from_json::<Value>(
+            "reading",
+            &canonical(json!({ "value": 1.0 })),
+            &json!({ "value": 1 }),
+        );
+        assert!(update.patch().0.is_empty());
+    }
+
+    #[test]
+    fn floats_beyond_the_exact_integers_convert_exactly() {
+        let beyond = 2f64.powi(53) + 2.0;
+        assert_eq!(canonical(json!(beyond)), json!(9_007_199_254_740_994_i64));
+        assert_eq!(canonical(json!(-2f64.powi(63))), json!(i64::MIN));
+        assert_eq!(canonical(json!(2f64.powi(63))), json!(1_u64 << 63));
+    }
+
+    #[test]
+    fn integers_and_floats_out_of_range_are_kept() {
+        assert_eq!(canonical(json!(u64::MAX)), json!(u64::MAX));
+        assert_eq!(canonical(json!(i64::MIN)), json!(i64::MIN));
+        // `u64::MAX as f64` is 2^64, past the largest `u64`
+        let past = u64::MAX as f64;
+        assert!(canonical(json!(past)).is_f64());
+        assert!(canonical(json!(-1e300)).is_f64());
+    }
+
+    #[test]
+    fn nan_is_not_a_json_number() {
+        // Json has no NaN, so serde turns it into null rather than a number to canonicalize
+        assert!(serde_json::Number::from_f64(f64::NAN).is_none());
+        assert_eq!(canonical(json!(f64::NAN)), Value::Null);
+        assert_eq!(canonical(json!([f64::INFINITY])), json!([null]));
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Empty,
+        Circle { radius: u32 },
+        Square { side: u32 },
+    }
+
+    fn json<T: Serialize>(value: T) -> Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    #[test]
+    fn switching_variants_changes_shape() {
+        let empty = json(Shape::Empty);
+        let circle = json(Shape::Circle { radius: 1 });
+        let square = json(Shape::Square { side: 1 });
+        assert!(shape_changed(&empty, &circle));
+        assert!(shape_changed(&circle, &empty));
+        assert!(shape_changed(&circle, &square));
+        assert!(!shape_changed(&circle, &json(Shape::Circle { radius: 2 })));
+    }
+
+    #[test]
+    fn map_with_a_single_key_changes_shape_with_its_key() {
+        assert!(shape_changed(&json!({ "a": 1 }), &json!({ "b": 1 })));
+        assert!(!shape_changed(&json!({ "a": 1 }), &json!({ "a": 2 })));
+        // Only maps with a single key are taken for enum variants
+        assert!(!shape_changed(
+            &json!({ "a": 1 }),
+            &json!({ "a": 1, "b": 2 })
+        ));
+        assert!(!shape_changed(
+            &json!({ "a": 1, "b": 2 }),
+            &json!({ "c": 3, "d": 4 })
+        ));
+    }
+
+    #[test]
+    fn every_strategy_snapshots_a_change_of_shape() {
+        let circle = json(Shape::Circle { radius: 1 });
+        let square = json(Shape::Square { side: 1 });
+        let strategies = [
+            DiffStrategy::Patch,
+            DiffStrategy::Bounded { max_operations: 8 },
+            DiffStrategy::Smallest,
+            DiffStrategy::Snapshot,
+        ];
+        for strategy in strategies {
+            let update = strategy.diff("shape", &circle, &square).unwrap();
+            assert_eq!(update.snapshot_value(), Some(&square), "{strategy:?}");
+            assert!(update.patch().0.is_empty(), "{strategy:?}");
+        }
+        let update = DiffStrategy::Patch
+            .diff("shape", &circle, &json(Shape::Circle { radius: 2 }))
+            .unwrap();
+        assert_eq!(update.snapshot_value(), None);
+    }
+}
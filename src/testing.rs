@@ -0,0 +1,537 @@
+//! An in-memory SSE connection, to test server signals without a browser.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use leptos::{provide_context, use_context};
+use web_sys::EventSource;
+
+use crate::{
+    finish_resync, handle_message, handle_server_error, ServerSignalBatch, ServerSignalError,
+    ServerSignalEventSourceContext, ServerSignalUpdate, Source, SseOptions,
+};
+
+/// A SSE connection which delivers the updates pushed to it, rather than those of a server.
+///
+/// It is provided in place of [`provide_sse`](crate::provide_sse), and applies the updates the
+/// same way, so the server signals created with it can be tested on native targets. Updates
+/// pushed before a signal is created are queued, as they are for a real connection.
+///
+/// Outside the browser, [`provide_sse`](crate::provide_sse) and its variants provide one too,
+/// so app code which provides its own SSE can be tested unchanged, see
+/// [`MockSse::from_context`].
+///
+/// A reactive runtime must be running, as for any signal.
+///
+/// # Example
+///
+/// ```
+/// # use leptos::SignalGetUntracked;
+/// # use leptos_sse::ServerSignalUpdate;
+/// # #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// let runtime = leptos::create_runtime();
+/// let sse = leptos_sse::MockSse::provide();
+/// let count = leptos_sse::create_sse_signal::<Count>("counter");
+///
+/// let update = ServerSignalUpdate::new("counter", &Count { value: 0 }, &Count { value: 1 }).unwrap();
+/// sse.push(update);
+/// assert_eq!(count.get_untracked().value, 1);
+///
+/// runtime.dispose();
+/// ```
+#[derive(Clone)]
+pub struct MockSse {
+    es: ServerSignalEventSourceContext,
+}
+
+impl MockSse {
+    /// Provides a mock SSE connection in the current reactive owner.
+    ///
+    /// The connection starts open.
+    pub fn provide() -> Self {
+        Self::provide_with_settings(SseOptions::default())
+    }
+
+    /// Provides a mock SSE connection with the settings given to
+    /// [`provide_sse`](crate::provide_sse), which calls this outside the browser.
+    ///
+    /// A mock connection which is already provided is kept, so the updates pushed to it reach
+    /// the server signals of the app too.
+    pub(crate) fn provide_with_settings(settings: SseOptions) -> Self {
+        if let Some(sse) = Self::from_context() {
+            *sse.es.settings.borrow_mut() = settings;
+            return sse;
+        }
+        let source = MockSource::default();
+        let es = ServerSignalEventSourceContext::new(Source::Mock(source), settings);
+        provide_context(es.clone());
+        MockSse { es }
+    }
+
+    /// Returns the mock SSE connection provided in the current reactive owner, by
+    /// [`MockSse::provide`] or by the app calling [`provide_sse`](crate::provide_sse).
+    ///
+    /// This lets a test push updates into the server signals of unchanged app code:
+    ///
+    /// ```
+    /// # use leptos::{ReadSignal, SignalGetUntracked};
+    /// # use leptos_sse::ServerSignalUpdate;
+    /// # #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+    /// # pub struct Count {
+    /// #     pub value: i32,
+    /// # }
+    /// # struct App;
+    /// # impl App {
+    /// #     fn counter() -> ReadSignal<Count> {
+    /// #         leptos_sse::provide_sse("/sse").unwrap();
+    /// #         leptos_sse::create_sse_signal("counter")
+    /// #     }
+    /// # }
+    /// let runtime = leptos::create_runtime();
+    /// let count = App::counter(); // calls `provide_sse` and `create_sse_signal`
+    ///
+    /// let sse = leptos_sse::MockSse::from_context().unwrap();
+    /// sse.push(ServerSignalUpdate::new("counter", &Count { value: 0 }, &Count { value: 1 }).unwrap());
+    /// assert_eq!(count.get_untracked().value, 1);
+    ///
+    /// runtime.dispose();
+    /// ```
+    pub fn from_context() -> Option<Self> {
+        let es = use_context::<ServerSignalEventSourceContext>()?;
+        match es.source() {
+            Source::Mock(_) => Some(MockSse { es }),
+            _ => None,
+        }
+    }
+
+    /// Delivers `update` as if the server sent it.
+    pub fn push(&self, update: ServerSignalUpdate) {
+        crate::apply_updates(&self.es, vec![update]);
+    }
+
+    /// Delivers the updates of `batch` as if the server sent them in a single event.
+    pub fn push_batch(&self, batch: ServerSignalBatch) {
+        match serde_json::to_string(&batch) {
+            Ok(data) => handle_message(&self.es, "message", &data),
+            Err(err) => panic!("server signal batches serialize to json: {err}"),
+        }
+    }
+
+    /// Delivers the data of an event named `event_type`, as the server encoded it.
+    ///
+    /// This decodes the data the way the client does, including the codec prefixes and
+    /// compression.
+    pub fn push_event(&self, event_type: &str, data: &str) {
+        handle_message(&self.es, event_type, data);
+    }
+
+    /// Delivers `error` as if the server reported it in an `error` event.
+    pub fn push_error(&self, error: ServerSignalError) {
+        match serde_json::to_string(&error) {
+            Ok(data) => handle_server_error(&self.es, &data),
+            Err(err) => panic!("server signal errors serialize to json: {err}"),
+        }
+    }
+
+    /// Responds with `snapshot` to the request of the client to resync its signal, as the url
+    /// set by [`provide_sse_resync_url`](crate::provide_sse_resync_url) or
+    /// [`sse_snapshot`](crate::sse_snapshot) would.
+    ///
+    /// The updates to the signal are dropped while its resync is pending. Nothing is done if
+    /// no resync of the signal was requested.
+    pub fn respond_resync(&self, snapshot: ServerSignalUpdate) {
+        let name = snapshot.name.clone();
+        finish_resync(&self.es, name, Ok(snapshot));
+    }
+
+    /// The number of times the client reconnected, for example to resync its signals after
+    /// missing updates.
+    pub fn reconnects(&self) -> usize {
+        match self.es.source() {
+            Source::Mock(source) => source.reconnects.get(),
+            _ => 0,
+        }
+    }
+}
+
+/// The state of a [`MockSse`], standing in for its event source.
+#[derive(Clone, Debug)]
+pub(crate) struct MockSource {
+    ready_state: Rc<Cell<u16>>,
+    reconnects: Rc<Cell<usize>>,
+}
+
+impl Default for MockSource {
+    fn default() -> Self {
+        MockSource {
+            ready_state: Rc::new(Cell::new(EventSource::OPEN)),
+            reconnects: Rc::default(),
+        }
+    }
+}
+
+impl MockSource {
+    pub(crate) fn ready_state(&self) -> u16 {
+        self.ready_state.get()
+    }
+
+    pub(crate) fn close(&self) {
+        self.ready_state.set(EventSource::CLOSED);
+    }
+
+    pub(crate) fn reconnect(&self) {
+        self.ready_state.set(EventSource::OPEN);
+        self.reconnects.set(self.reconnects.get() + 1);
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use leptos::{create_runtime, SignalGetUntracked};
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{create_sse_signal, Patch};
+
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Count {
+        value: i32,
+    }
+
+    /// The update of `counter` from `old` to `new`.
+    fn counted(old: i32, new: i32) -> ServerSignalUpdate {
+        ServerSignalUpdate::new("counter", &Count { value: old }, &Count { value: new }).unwrap()
+    }
+
+    #[test]
+    fn applies_patches_in_order() {
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+        let count = create_sse_signal::<Count>("counter");
+
+        sse.push(counted(0, 1));
+        assert_eq!(count.get_untracked(), Count { value: 1 });
+        sse.push_batch(ServerSignalBatch::new([counted(1, 2), counted(2, 3)]));
+        assert_eq!(count.get_untracked(), Count { value: 3 });
+        sse.push_event("counter", &serde_json::to_string(&counted(3, 4)).unwrap());
+        assert_eq!(count.get_untracked(), Count { value: 4 });
+        assert_eq!(sse.reconnects(), 0);
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn version_gap_resyncs() {
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+        let count = create_sse_signal::<Count>("counter");
+
+        sse.push(versioned(1, json!(1)));
+        sse.push(versioned(2, json!(2)));
+        assert_eq!(sse.reconnects(), 0);
+        // Version 3 was missed, so the patch is not applied
+        sse.push(versioned(4, json!(4)));
+        assert_eq!(count.get_untracked(), Count { value: 2 });
+        assert_eq!(sse.reconnects(), 1);
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn drops_updates_while_the_resync_is_fetched() {
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+        crate::provide_sse_resync_url("/sse/resync");
+        let count = create_sse_signal::<Count>("counter");
+
+        sse.push(versioned(1, json!(1)));
+        sse.push(versioned(2, json!(2)));
+        // Version 3 was missed, so neither it nor the updates after it apply to the value
+        sse.push(versioned(4, json!(4)));
+        sse.push(versioned(5, json!(5)));
+        assert_eq!(count.get_untracked(), Count { value: 2 });
+
+        let snapshot = ServerSignalUpdate::snapshot("counter", &Count { value: 5 }).unwrap();
+        sse.respond_resync(snapshot);
+        assert_eq!(count.get_untracked(), Count { value: 5 });
+        // The next update follows the last one the snapshot holds
+        sse.push(versioned(6, json!(6)));
+        assert_eq!(count.get_untracked(), Count { value: 6 });
+        sse.push(versioned(8, json!(8)));
+        assert_eq!(count.get_untracked(), Count { value: 6 });
+        assert_eq!(sse.reconnects(), 0);
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn delays_updates_until_the_signal_is_created() {
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+
+        sse.push(counted(0, 1));
+        sse.push(counted(1, 2));
+        let count = create_sse_signal::<Count>("counter");
+        assert_eq!(count.get_untracked(), Count { value: 2 });
+
+        runtime.dispose();
+    }
+
+    #[test]
+    #[cfg(all(debug_assertions, not(feature = "in-place-patch")))]
+    fn rolls_back_values_which_do_not_deserialize() {
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+        let count = create_sse_signal::<Count>("counter");
+
+        sse.push(counted(0, 1));
+        sse.push(versioned(0, json!("not a number")));
+        assert_eq!(count.get_untracked(), Count { value: 1 });
+        let errors = crate::sse_error_log().get_untracked();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind(), crate::AppliedPatchErrorKind::Deserialize);
+        // The json value was rolled back too, so the next patch still applies
+        sse.push(counted(1, 2));
+        assert_eq!(count.get_untracked(), Count { value: 2 });
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn reports_server_errors() {
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+        let _count = create_sse_signal::<Count>("counter");
+
+        let error = ServerSignalError::new("counter", "the stream failed");
+        sse.push_error(error.clone());
+        assert_eq!(crate::sse_last_error().get_untracked(), Some(error));
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn delivers_default_and_named_events() {
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+        let count = create_sse_signal::<Count>("counter");
+        let event = |update: &ServerSignalUpdate| serde_json::to_string(update).unwrap();
+
+        // Unnamed events update any signal
+        sse.push_event("message", &event(&counted(0, 1)));
+        assert_eq!(count.get_untracked(), Count { value: 1 });
+        // Named events only update the signal they are named after
+        sse.push_event("counter", &event(&counted(1, 2)));
+        assert_eq!(count.get_untracked(), Count { value: 2 });
+        sse.push_event("other", &event(&counted(2, 3)));
+        assert_eq!(count.get_untracked(), Count { value: 2 });
+        let batch = ServerSignalBatch::new([counted(2, 3)]);
+        sse.push_event("counter", &serde_json::to_string(&batch).unwrap());
+        assert_eq!(count.get_untracked(), Count { value: 2 });
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn delivers_events_of_the_types_listened_to() {
+        let runtime = create_runtime();
+        crate::provide_sse_with("/sse", SseOptions::new().with_events(&["update"])).unwrap();
+        let sse = MockSse::from_context().unwrap();
+        let count = create_sse_signal::<Count>("counter");
+
+        let batch = ServerSignalBatch::new([counted(0, 1), counted(1, 2)]);
+        sse.push_event("update", &serde_json::to_string(&batch).unwrap());
+        assert_eq!(count.get_untracked(), Count { value: 2 });
+        sse.push_event("update", &serde_json::to_string(&counted(2, 3)).unwrap());
+        assert_eq!(count.get_untracked(), Count { value: 3 });
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn skipped_and_flattened_fields_come_and_go() {
+        use std::collections::BTreeMap;
+
+        #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+        struct Reading {
+            value: i32,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            note: Option<String>,
+            #[serde(flatten)]
+            extra: BTreeMap<String, i32>,
+        }
+
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+        let reading = create_sse_signal::<Reading>("reading");
+
+        let with_fields = Reading {
+            value: 1,
+            note: Some("calibrated".to_owned()),
+            extra: BTreeMap::from([("humidity".to_owned(), 40)]),
+        };
+        let without_fields = Reading {
+            value: 2,
+            ..Reading::default()
+        };
+        let mut old = Reading::default();
+        for new in [with_fields.clone(), without_fields, with_fields] {
+            let update = ServerSignalUpdate::new("reading", &old, &new).unwrap();
+            sse.push(update);
+            assert_eq!(reading.get_untracked(), new);
+            old = new;
+        }
+        assert_eq!(sse.reconnects(), 0);
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn failed_patch_leaves_the_signal_unset() {
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+        let count = create_sse_signal::<Count>("counter");
+
+        // The first operation applies, but the second does not
+        let patch: Patch = serde_json::from_value(json!([
+            { "op": "replace", "path": "/value", "value": 1 },
+            { "op": "replace", "path": "/missing", "value": 2 },
+        ]))
+        .unwrap();
+        sse.push(ServerSignalUpdate::from_parts("counter", patch));
+        assert_eq!(count.get_untracked(), Count { value: 0 });
+        // A value patched in place cannot be rolled back, so it is resynced
+        assert_eq!(
+            sse.reconnects(),
+            usize::from(cfg!(feature = "in-place-patch"))
+        );
+
+        runtime.dispose();
+    }
+
+    /// An update of `counter` at `version`, replacing its value with `value`.
+    fn versioned(version: u64, value: serde_json::Value) -> ServerSignalUpdate {
+        serde_json::from_value(json!({
+            "name": "counter",
+            "patch": [{ "op": "replace", "path": "/value", "value": value }],
+            "version": version,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    #[cfg(all(debug_assertions, not(feature = "in-place-patch")))]
+    fn rolled_back_update_is_not_taken_as_applied() {
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+        let count = create_sse_signal::<Count>("counter");
+
+        sse.push(versioned(1, json!(1)));
+        sse.push(versioned(2, json!("not a number")));
+        assert_eq!(count.get_untracked(), Count { value: 1 });
+        // The next update is diffed against the value rolled back, so it is missing one
+        sse.push(versioned(3, json!(3)));
+        assert_eq!(count.get_untracked(), Count { value: 1 });
+        assert_eq!(sse.reconnects(), 1);
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn signal_which_does_not_serialize_keeps_its_value() {
+        use std::collections::BTreeMap;
+
+        let runtime = create_runtime();
+        let _sse = MockSse::provide();
+        // Json objects only have string keys
+        let initial = BTreeMap::from([(vec![1u8], 1)]);
+        let signal = crate::create_sse_signal_with_initial("keyed", initial.clone());
+        assert_eq!(signal.get_untracked(), initial);
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn field_is_only_updated_when_it_changed() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        use leptos::{create_effect, SignalWith};
+
+        #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+        struct Document {
+            title: String,
+            views: u64,
+        }
+
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+        let title = crate::create_sse_field::<Document, String>("document", "/title");
+        let runs = Rc::new(Cell::new(0));
+        create_effect({
+            let runs = runs.clone();
+            move |_| title.with(|_| runs.set(runs.get() + 1))
+        });
+        assert_eq!(title.get_untracked(), Some(String::new()));
+
+        let first = Document {
+            title: "title".to_owned(),
+            views: 1,
+        };
+        let second = Document {
+            views: 2,
+            ..first.clone()
+        };
+        sse.push(ServerSignalUpdate::new("document", &Document::default(), &first).unwrap());
+        sse.push(ServerSignalUpdate::new("document", &first, &second).unwrap());
+        assert_eq!(title.get_untracked(), Some("title".to_owned()));
+        // Once created, and once the title changed
+        assert_eq!(runs.get(), 2);
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn field_and_signal_share_their_value() {
+        let runtime = create_runtime();
+        let sse = MockSse::provide();
+        let value = crate::create_sse_field::<Count, i32>("counter", "/value");
+
+        sse.push(counted(0, 1));
+        assert_eq!(value.get_untracked(), Some(1));
+        // Created after the field, from the value the field follows
+        let count = create_sse_signal::<Count>("counter");
+        assert_eq!(count.get_untracked(), Count { value: 1 });
+        sse.push(counted(1, 2));
+        assert_eq!(count.get_untracked(), Count { value: 2 });
+        assert_eq!(value.get_untracked(), Some(2));
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn field_of_a_value_which_does_not_serialize_is_missing() {
+        use std::collections::BTreeMap;
+
+        // Json objects only have string keys
+        #[derive(Serialize)]
+        struct Keyed(BTreeMap<Vec<u8>, i32>);
+
+        impl Default for Keyed {
+            fn default() -> Self {
+                Keyed(BTreeMap::from([(vec![1u8], 1)]))
+            }
+        }
+
+        let runtime = create_runtime();
+        let _sse = MockSse::provide();
+        let field = crate::create_sse_field::<Keyed, i32>("keyed", "/0");
+        assert_eq!(field.get_untracked(), None);
+
+        runtime.dispose();
+    }
+}
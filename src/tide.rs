@@ -0,0 +1,125 @@
+use std::future::poll_fn;
+use std::pin::pin;
+
+use futures::stream::TryStream;
+use serde::Serialize;
+use tide::StatusCode;
+pub use tokio::sync::mpsc::error::{SendError, TrySendError};
+
+use crate::server::{event_id, sealed, Backend, SignalEvent};
+pub use crate::server::{BroadcastSender, LossySender, UnboundedSender};
+
+/// Alias for a type-erased error type.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The [`Backend`] of tide, whose events are the name and data written to a
+/// [`tide::sse::Sender`].
+///
+/// Tide does not run on tokio, whose timers throttle the updates and send heartbeats, and its
+/// sender has no retry or comment event, so neither
+/// [`ServerSentEvents::throttle`](crate::ServerSentEvents::throttle) nor
+/// [`ServerSentEvents::with_retry`](crate::ServerSentEvents::with_retry) are offered.
+#[derive(Clone, Copy, Debug)]
+pub struct Tide;
+
+impl sealed::Sealed for Tide {}
+
+impl Backend for Tide {
+    type Error = BoxError;
+    type Event = (String, String);
+}
+
+/// A signal owned by the server which writes to the SSE when mutated.
+///
+/// Tide's SSE are push based, so the updates are written to a [`tide::sse::Sender`] with
+/// [`ServerSentEvents::send_to`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use leptos_sse::tide::ServerSentEvents;
+/// # mod async_std {
+/// #     pub mod task {
+/// #         pub fn spawn(_future: impl std::future::Future + Send + 'static) {}
+/// #     }
+/// # }
+/// # #[derive(Default, serde::Serialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// # let mut app = tide::new();
+/// app.at("/sse").get(tide::sse::endpoint(|_req, sender| async move {
+///     let (tx, events) = ServerSentEvents::<()>::channel::<Count>("counter", 1)?;
+///     async_std::task::spawn(async move {
+///         // Send updates with `tx`
+///     });
+///     events.send_to(&sender).await
+/// }));
+/// ```
+pub type ServerSentEvents<S> = crate::server::ServerSentEvents<S, Tide>;
+
+/// Sender half of a server-sent events stream, whose custom events are the name and data of
+/// the event.
+pub type Sender<T> = crate::server::Sender<T, Tide>;
+
+/// Creates the SSE of each connection of a broadcast channel, see
+/// [`ServerSentEvents::broadcast_channel`].
+pub type BroadcastSubscriber<T> = crate::server::BroadcastSubscriber<T, Tide>;
+
+/// The stream of a [`ServerSentEvents`] created by [`ServerSentEvents::channel_for`].
+pub type ChannelStream<T> = crate::server::ChannelStream<T, Tide>;
+
+/// The stream of a [`ServerSentEvents`] created by [`BroadcastSubscriber::subscribe`].
+pub type BroadcastChannelStream<T> = crate::server::BroadcastChannelStream<T, Tide>;
+
+impl<S> ServerSentEvents<S> {
+    /// Write the updates to `sender` until the stream ends.
+    ///
+    /// This fails when the client disconnects, or when an update cannot be encoded.
+    pub async fn send_to(self, sender: &tide::sse::Sender) -> tide::Result<()>
+    where
+        S: TryStream<Error = BoxError>,
+        S::Ok: Serialize,
+    {
+        let mut events = pin!(self);
+        while let Some(event) = poll_fn(|cx| events.as_mut().poll_signal(cx)).await {
+            match event {
+                // Tide's sender only sets the id of the event from its metadata
+                Ok(SignalEvent::Update {
+                    id,
+                    name,
+                    data,
+                    meta,
+                }) => {
+                    // Tide writes the data as a single field, so each further line needs a
+                    // field of its own, down to a trailing empty one
+                    let data = data
+                        .as_str()
+                        .split('\n')
+                        .collect::<Vec<_>>()
+                        .join("\ndata:");
+                    let id = event_id(id, meta.as_ref());
+                    sender.send(&name, data, Some(&id)).await?;
+                }
+                Ok(SignalEvent::Error(error)) => {
+                    sender
+                        .send("error", serde_json::to_string(&error)?, None)
+                        .await?;
+                }
+                // Tide's sender has no retry or comment event, so neither `with_retry` nor
+                // `with_heartbeat` is offered
+                Ok(SignalEvent::Retry(_) | SignalEvent::Heartbeat) => {}
+                Ok(SignalEvent::Close(name)) => sender.send("close", name, None).await?,
+                // Sent by `Sender::send_event` as the name and data of the event
+                Ok(SignalEvent::Custom((name, data))) => {
+                    let data = data.split('\n').collect::<Vec<_>>().join("\ndata:");
+                    sender.send(&name, data, None).await?;
+                }
+                Err(err) => {
+                    return Err(tide::Error::from_str(StatusCode::InternalServerError, err))
+                }
+            }
+        }
+        Ok(())
+    }
+}
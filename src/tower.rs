@@ -0,0 +1,197 @@
+use std::fmt::Write;
+use std::pin::Pin;
+use std::task::Poll;
+
+use bytes::Bytes;
+use futures::stream::{Stream, TryStream};
+use serde::Serialize;
+pub use tokio::sync::mpsc::error::{SendError, TrySendError};
+
+use crate::server::{
+    event_id, sealed, Backend, EventMeta, RetryBackend, SignalEvent, TimerBackend,
+};
+pub use crate::server::{BroadcastSender, LossySender, UnboundedSender};
+
+/// Alias for a type-erased error type.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The [`Backend`] producing the SSE as text, without depending on a web framework.
+///
+/// Its events are frames of [`Bytes`], so the events sent with
+/// [`Sender::send_event`](crate::Sender::send_event) must be complete, ending with an empty
+/// line, for example `"event: notification\ndata: hello\n\n"`.
+#[derive(Clone, Copy, Debug)]
+pub struct Tower;
+
+impl sealed::Sealed for Tower {}
+
+impl Backend for Tower {
+    type Error = BoxError;
+    type Event = Bytes;
+}
+
+impl RetryBackend for Tower {}
+
+impl TimerBackend for Tower {}
+
+/// A signal owned by the server which writes to the SSE when mutated.
+///
+/// This does not depend on a web framework: it produces the SSE as text, one [`Bytes`] per
+/// event, which can be used as the body of any `text/event-stream` response.
+pub type ServerSentEvents<S> = crate::server::ServerSentEvents<S, Tower>;
+
+/// Sender half of a server-sent events stream, whose custom events are frames of [`Bytes`].
+pub type Sender<T> = crate::server::Sender<T, Tower>;
+
+/// Creates the SSE of each connection of a broadcast channel, see
+/// [`ServerSentEvents::broadcast_channel`].
+pub type BroadcastSubscriber<T> = crate::server::BroadcastSubscriber<T, Tower>;
+
+/// The stream of a [`ServerSentEvents`] created by [`ServerSentEvents::channel_for`].
+pub type ChannelStream<T> = crate::server::ChannelStream<T, Tower>;
+
+/// The stream of a [`ServerSentEvents`] created by [`BroadcastSubscriber::subscribe`].
+pub type BroadcastChannelStream<T> = crate::server::BroadcastChannelStream<T, Tower>;
+
+impl<S> Stream for ServerSentEvents<S>
+where
+    S: TryStream<Error = BoxError>,
+    S::Ok: Serialize,
+{
+    type Item = Result<Bytes, BoxError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let mut frame = String::new();
+        match self.poll_signal(cx) {
+            Poll::Ready(Some(Ok(SignalEvent::Update {
+                id,
+                name,
+                data,
+                meta,
+            }))) => {
+                if let Some(comment) = meta.as_ref().and_then(EventMeta::comment) {
+                    write_field(&mut frame, "", comment);
+                }
+                if let Some(retry) = meta.as_ref().and_then(EventMeta::retry) {
+                    write_field(&mut frame, "retry", &retry.as_millis().to_string());
+                }
+                write_field(&mut frame, "id", &event_id(id, meta.as_ref()));
+                write_field(&mut frame, "event", &name);
+                write_field(&mut frame, "data", data.as_str());
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Error(error)))) => {
+                write_field(&mut frame, "event", "error");
+                write_field(&mut frame, "data", &serde_json::to_string(&error)?);
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Retry(retry)))) => {
+                write_field(&mut frame, "retry", &retry.as_millis().to_string());
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Heartbeat))) => frame.push(':'),
+            Poll::Ready(Some(Ok(SignalEvent::Close(name)))) => {
+                write_field(&mut frame, "event", "close");
+                write_field(&mut frame, "data", &name);
+            }
+            // Sent by `Sender::send_event` as a complete frame
+            Poll::Ready(Some(Ok(SignalEvent::Custom(event)))) => {
+                return Poll::Ready(Some(Ok(event)))
+            }
+            Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        }
+        frame.push('\n');
+        Poll::Ready(Some(Ok(Bytes::from(frame))))
+    }
+}
+
+/// Writes a field of an event, splitting `value` over several fields if it spans several lines.
+fn write_field(frame: &mut String, field: &str, value: &str) {
+    // Unlike `str::lines`, this keeps a trailing empty line
+    for line in value.split('\n') {
+        if line.is_empty() {
+            let _ = writeln!(frame, "{field}:");
+        } else {
+            let _ = writeln!(frame, "{field}: {line}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::executor::block_on;
+    use futures::{stream, StreamExt};
+
+    use super::*;
+
+    #[test]
+    fn sends_custom_events_between_updates() {
+        block_on(async {
+            let (tx, events) = ServerSentEvents::<()>::channel::<i32>("counter", 4).unwrap();
+            tx.send(1).await.unwrap();
+            tx.send_event(Bytes::from_static(b"event: notification\ndata: hello\n\n"))
+                .await
+                .unwrap();
+            tx.send(2).await.unwrap();
+            drop(tx);
+
+            let frames = events
+                .map(|frame| String::from_utf8(frame.unwrap().to_vec()).unwrap())
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(frames.len(), 3);
+            assert!(frames[0].contains("event: counter\n"));
+            assert_eq!(frames[1], "event: notification\ndata: hello\n\n");
+            assert!(frames[2].contains("event: counter\n"));
+        });
+    }
+
+    fn always_ready() -> impl TryStream<Ok = i32, Error = BoxError> {
+        let mut value = 0;
+        stream::repeat_with(move || {
+            value += 1;
+            Ok(value)
+        })
+    }
+
+    #[test]
+    fn multiplexed_signals_send_the_same_ids() {
+        block_on(async {
+            let counter = ServerSentEvents::new("counter", stream::iter([Ok::<_, BoxError>(1)]));
+            let total = ServerSentEvents::new("total", stream::iter([Ok::<_, BoxError>(2)]));
+            let frames = ServerSentEvents::multiplex([counter.unwrap(), total.unwrap()])
+                .map(|frame| String::from_utf8(frame.unwrap().to_vec()).unwrap())
+                .collect::<Vec<_>>()
+                .await;
+            let ids = frames
+                .iter()
+                .filter_map(|frame| frame.lines().find(|line| line.starts_with("id: ")))
+                .collect::<Vec<_>>();
+            // Each id is the version of its signal, which a resumed stream could not tell apart
+            assert_eq!(ids, ["id: 1", "id: 1"]);
+        });
+    }
+
+    #[tokio::test]
+    async fn throttles_a_source_which_is_always_ready() {
+        let events = ServerSentEvents::new("counter", always_ready())
+            .unwrap()
+            .throttle(Duration::from_millis(10));
+        let frames = events.take(3).collect::<Vec<_>>().await;
+        assert_eq!(frames.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn debounces_a_source_which_is_always_ready() {
+        let mut events = ServerSentEvents::new("counter", always_ready())
+            .unwrap()
+            .debounce(Duration::from_millis(10));
+        // The source is never quiet, so nothing is sent, but polling it still yields
+        let next = tokio::time::timeout(Duration::from_millis(50), events.next()).await;
+        assert!(next.is_err());
+    }
+}
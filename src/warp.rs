@@ -0,0 +1,130 @@
+use std::pin::Pin;
+use std::task::Poll;
+
+use futures::stream::{Stream, TryStream};
+use serde::Serialize;
+pub use tokio::sync::mpsc::error::{SendError, TrySendError};
+use warp::sse::Event;
+
+use crate::server::{
+    event_id, sealed, Backend, EventMeta, RetryBackend, SignalEvent, TimerBackend,
+};
+pub use crate::server::{BroadcastSender, LossySender, UnboundedSender};
+
+/// Alias for a type-erased error type.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The [`Backend`] of warp, whose events are [`Event`]s.
+#[derive(Clone, Copy, Debug)]
+pub struct Warp;
+
+impl sealed::Sealed for Warp {}
+
+impl Backend for Warp {
+    type Error = BoxError;
+    type Event = Event;
+}
+
+impl RetryBackend for Warp {}
+
+impl TimerBackend for Warp {}
+
+/// A signal owned by the server which writes to the SSE when mutated, as a stream of
+/// [`Event`]s for `warp::sse::reply`.
+pub type ServerSentEvents<S> = crate::server::ServerSentEvents<S, Warp>;
+
+/// Sender half of a server-sent events stream, whose custom events are [`Event`]s.
+pub type Sender<T> = crate::server::Sender<T, Warp>;
+
+/// Creates the SSE of each connection of a broadcast channel, see
+/// [`ServerSentEvents::broadcast_channel`].
+pub type BroadcastSubscriber<T> = crate::server::BroadcastSubscriber<T, Warp>;
+
+/// The stream of a [`ServerSentEvents`] created by [`ServerSentEvents::channel_for`].
+pub type ChannelStream<T> = crate::server::ChannelStream<T, Warp>;
+
+/// The stream of a [`ServerSentEvents`] created by [`BroadcastSubscriber::subscribe`].
+pub type BroadcastChannelStream<T> = crate::server::BroadcastChannelStream<T, Warp>;
+
+impl<S> Stream for ServerSentEvents<S>
+where
+    S: TryStream<Error = BoxError>,
+    S::Ok: Serialize,
+{
+    type Item = Result<Event, SseError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        match self.poll_signal(cx) {
+            Poll::Ready(Some(Ok(SignalEvent::Update {
+                id,
+                name,
+                data,
+                meta,
+            }))) => {
+                let mut event = Event::default()
+                    .id(event_id(id, meta.as_ref()))
+                    .event(name)
+                    .data(data.as_str());
+                if let Some(retry) = meta.as_ref().and_then(EventMeta::retry) {
+                    event = event.retry(retry);
+                }
+                if let Some(comment) = meta.as_ref().and_then(EventMeta::comment) {
+                    event = event.comment(comment);
+                }
+                Poll::Ready(Some(Ok(event)))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Error(error)))) => {
+                let event = Event::default()
+                    .event("error")
+                    .data(serde_json::to_string(&error).map_err(SseError::new)?);
+                Poll::Ready(Some(Ok(event)))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Retry(retry)))) => {
+                Poll::Ready(Some(Ok(Event::default().retry(retry))))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Heartbeat))) => {
+                Poll::Ready(Some(Ok(Event::default().comment(""))))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Close(name)))) => {
+                Poll::Ready(Some(Ok(Event::default().event("close").data(name))))
+            }
+            Poll::Ready(Some(Ok(SignalEvent::Custom(event)))) => Poll::Ready(Some(Ok(event))),
+            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(SseError(err)))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// An error produced by a [`ServerSentEvents`] stream.
+///
+/// This wraps a [`BoxError`], as `warp::sse::reply` requires the error type of the stream to
+/// implement [`std::error::Error`].
+#[derive(Debug)]
+pub struct SseError(BoxError);
+
+impl SseError {
+    fn new(err: impl Into<BoxError>) -> Self {
+        SseError(err.into())
+    }
+
+    /// Returns the underlying error.
+    pub fn into_inner(self) -> BoxError {
+        self.0
+    }
+}
+
+impl std::fmt::Display for SseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for SseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.0)
+    }
+}